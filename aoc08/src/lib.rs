@@ -0,0 +1,587 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Error as IoError};
+use std::mem;
+use std::num::ParseIntError;
+use std::path::Path;
+use std::string::FromUtf8Error;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Node {
+    id: usize,
+    children: Vec<Node>,
+    metadata: Vec<u8>,
+}
+
+impl Node {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    pub fn metadata(&self) -> &[u8] {
+        &self.metadata
+    }
+
+    pub fn iter_preorder(&self) -> Preorder<'_> {
+        Preorder { stack: vec![self] }
+    }
+
+    pub fn iter_postorder(&self) -> Postorder<'_> {
+        Postorder {
+            stack: vec![(self, 0)],
+        }
+    }
+}
+
+// The derived drop recurses once per level, which overflows the stack on deep
+// chains.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.children);
+
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+pub struct Preorder<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Preorder<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+
+        Some(node)
+    }
+}
+
+pub struct Postorder<'a> {
+    stack: Vec<(&'a Node, usize)>,
+}
+
+impl<'a> Iterator for Postorder<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        loop {
+            let (node, next_child) = self.stack.last_mut()?;
+
+            match node.children.get(*next_child) {
+                Some(child) => {
+                    *next_child += 1;
+                    self.stack.push((child, 0));
+                }
+                None => return self.stack.pop().map(|(node, _)| node),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub nodes: usize,
+    pub max_depth: usize,
+    pub metadata_entries: usize,
+    pub max_metadata: u8,
+}
+
+impl fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, depth {}, {} metadata entries (max {})",
+            self.nodes, self.max_depth, self.metadata_entries, self.max_metadata
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    ParseInt(ParseIntError),
+    FromUtf8(FromUtf8Error),
+    TrailingData { remaining: usize },
+    UnexpectedEof,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::ParseInt(e) => fmt::Display::fmt(e, f),
+            Error::FromUtf8(e) => fmt::Display::fmt(e, f),
+            Error::TrailingData { remaining } => {
+                write!(f, "{} trailing number(s) after the root node", remaining)
+            }
+            Error::UnexpectedEof => write!(f, "input ended in the middle of a node"),
+        }
+    }
+}
+
+struct Tokens<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Iterator for Tokens<R> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Result<u8, Error>> {
+        let mut token = Vec::new();
+
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok([]) => break,
+                Ok(buf) => buf,
+                Err(e) => return Some(Err(Error::Io(e))),
+            };
+
+            let skip = if token.is_empty() {
+                buf.iter().take_while(|b| b.is_ascii_whitespace()).count()
+            } else {
+                0
+            };
+            let len = buf[skip..]
+                .iter()
+                .take_while(|b| !b.is_ascii_whitespace())
+                .count();
+            let done = skip + len < buf.len();
+
+            token.extend_from_slice(&buf[skip..skip + len]);
+            self.reader.consume(skip + len);
+
+            if done && !token.is_empty() {
+                break;
+            }
+        }
+
+        if token.is_empty() {
+            return None;
+        }
+
+        Some(
+            String::from_utf8(token)
+                .map_err(Error::FromUtf8)
+                .and_then(|s| s.parse().map_err(Error::ParseInt)),
+        )
+    }
+}
+
+struct Frame {
+    child_len: usize,
+    meta_len: usize,
+    children: Vec<Node>,
+}
+
+fn next_token<R: BufRead>(tokens: &mut Tokens<R>) -> Result<u8, Error> {
+    tokens.next().unwrap_or(Err(Error::UnexpectedEof))
+}
+
+fn next_frame<R: BufRead>(tokens: &mut Tokens<R>) -> Result<Frame, Error> {
+    let child_len = next_token(tokens)? as usize;
+    let meta_len = next_token(tokens)? as usize;
+
+    Ok(Frame {
+        child_len,
+        meta_len,
+        children: Vec::with_capacity(child_len),
+    })
+}
+
+pub fn parse_stream<R: BufRead>(reader: R, lenient: bool) -> Result<Node, Error> {
+    let mut tokens = Tokens { reader };
+    let mut stack = vec![next_frame(&mut tokens)?];
+    let mut next_id = 0;
+
+    let root = loop {
+        let frame = stack.last_mut().unwrap();
+
+        if frame.children.len() < frame.child_len {
+            stack.push(next_frame(&mut tokens)?);
+            continue;
+        }
+
+        let metadata = (0..frame.meta_len)
+            .map(|_| next_token(&mut tokens))
+            .collect::<Result<_, _>>()?;
+        let Frame { children, .. } = stack.pop().unwrap();
+        let node = Node {
+            id: next_id,
+            children,
+            metadata,
+        };
+        next_id += 1;
+
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => break node,
+        }
+    };
+
+    if lenient {
+        return Ok(root);
+    }
+
+    match tokens.try_fold(0, |remaining, token| token.map(|_| remaining + 1))? {
+        0 => Ok(root),
+        remaining => Err(Error::TrailingData { remaining }),
+    }
+}
+
+pub fn parse_input(path: &Path, lenient: bool) -> Result<Node, Error> {
+    parse_stream(
+        BufReader::new(File::open(path).map_err(Error::Io)?),
+        lenient,
+    )
+}
+
+fn metadata_sum(node: &Node) -> u32 {
+    node.metadata
+        .iter()
+        .cloned()
+        .fold(0, |sum, x| sum + u32::from(x))
+}
+
+pub fn simple_sum(node: &Node) -> u64 {
+    node.iter_preorder()
+        .map(|node| u64::from(metadata_sum(node)))
+        .sum()
+}
+
+pub fn complex_sum(node: &Node) -> u64 {
+    let mut values = vec![0; node.id + 1];
+
+    for node in node.iter_postorder() {
+        values[node.id] = match node.children.len() {
+            0 => u64::from(metadata_sum(node)),
+            l => node
+                .metadata
+                .iter()
+                .cloned()
+                .filter_map(|x| {
+                    let x = x as usize;
+
+                    if x > 0 && x <= l {
+                        Some(values[node.children[x - 1].id])
+                    } else {
+                        None
+                    }
+                })
+                .sum(),
+        };
+    }
+
+    values[node.id]
+}
+
+pub fn stats(root: &Node) -> TreeStats {
+    let mut stats = TreeStats::default();
+    let mut stack = vec![(root, 1)];
+
+    while let Some((node, depth)) = stack.pop() {
+        stats.nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        stats.metadata_entries += node.metadata.len();
+        stats.max_metadata = node
+            .metadata
+            .iter()
+            .cloned()
+            .fold(stats.max_metadata, u8::max);
+        stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+    }
+
+    stats
+}
+
+pub fn to_license(root: &Node) -> String {
+    fn push(out: &mut String, value: impl fmt::Display) {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&value.to_string());
+    }
+
+    let mut out = String::new();
+    let mut stack = vec![(root, 0)];
+    push(&mut out, root.children.len());
+    push(&mut out, root.metadata.len());
+
+    while let Some((node, next_child)) = stack.last_mut() {
+        match node.children.get(*next_child) {
+            Some(child) => {
+                *next_child += 1;
+                push(&mut out, child.children.len());
+                push(&mut out, child.metadata.len());
+                stack.push((child, 0));
+            }
+            None => {
+                node.metadata.iter().for_each(|&x| push(&mut out, x));
+                stack.pop();
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+    use std::iter;
+
+    const EXAMPLE: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+
+    fn parse(s: &str) -> Result<Node, Error> {
+        parse_stream(Cursor::new(s), false)
+    }
+
+    #[test]
+    fn example() {
+        let root = parse(EXAMPLE).unwrap();
+
+        assert_eq!(simple_sum(&root), 138);
+        assert_eq!(complex_sum(&root), 66);
+    }
+
+    fn chain(depth: usize) -> String {
+        let mut s = "1 1 ".repeat(depth);
+        s.push_str("0 1 7");
+        s.push_str(&" 1".repeat(depth));
+        s
+    }
+
+    #[test]
+    fn traversal_order() {
+        let root = parse(EXAMPLE).unwrap();
+        let order = |nodes: Vec<&Node>| -> Vec<Vec<u8>> {
+            nodes.iter().map(|node| node.metadata().to_vec()).collect()
+        };
+
+        let (a, b, c, d) = (vec![1, 1, 2], vec![10, 11, 12], vec![2], vec![99]);
+        assert_eq!(
+            order(root.iter_preorder().collect()),
+            vec![a.clone(), b.clone(), c.clone(), d.clone()]
+        );
+        assert_eq!(order(root.iter_postorder().collect()), vec![b, d, c, a]);
+        assert_eq!(root.children().len(), 2);
+    }
+
+    #[test]
+    fn deep_chain() {
+        let depth = 50_000;
+        let root = parse(&chain(depth)).unwrap();
+
+        assert_eq!(root.iter_preorder().count(), depth + 1);
+        assert_eq!(root.iter_postorder().count(), depth + 1);
+        assert_eq!(root.iter_postorder().next().unwrap().metadata(), &[7]);
+        assert_eq!(simple_sum(&root), depth as u64 + 7);
+    }
+
+    // Every level refers to its only child `refs` times, so the naive recursion
+    // visits the leaf refs^depth times.
+    fn fan(depth: usize, refs: usize) -> String {
+        let mut s = format!("1 {} ", refs).repeat(depth);
+        s.push_str("0 1 5");
+        s.push_str(&" 1".repeat(refs * depth));
+        s
+    }
+
+    fn naive(node: &Node) -> u64 {
+        match node.children().len() {
+            0 => node.metadata().iter().map(|&x| u64::from(x)).sum(),
+            l => node
+                .metadata()
+                .iter()
+                .filter(|&&x| x > 0 && x as usize <= l)
+                .map(|&x| naive(&node.children()[x as usize - 1]))
+                .sum(),
+        }
+    }
+
+    #[test]
+    fn memoized_matches_naive() {
+        for &(depth, refs) in &[(1, 1), (3, 4), (5, 7), (2, 255)] {
+            let root = parse(&fan(depth, refs)).unwrap();
+            assert_eq!(complex_sum(&root), naive(&root));
+        }
+
+        let root = parse(&chain(50)).unwrap();
+        assert_eq!(complex_sum(&root), naive(&root));
+    }
+
+    #[test]
+    fn shared_subtree_stress() {
+        // 10^9 visits for the naive recursion
+        let root = parse(&fan(9, 10)).unwrap();
+        assert_eq!(complex_sum(&root), 5 * 10u64.pow(9));
+    }
+
+    #[test]
+    fn example_stats() {
+        let root = parse(EXAMPLE).unwrap();
+
+        assert_eq!(
+            stats(&root),
+            TreeStats {
+                nodes: 4,
+                max_depth: 3,
+                metadata_entries: 8,
+                max_metadata: 99,
+            }
+        );
+    }
+
+    #[test]
+    fn chain_stats() {
+        let stats = stats(&parse(&chain(1000)).unwrap());
+
+        assert_eq!((stats.nodes, stats.max_depth), (1001, 1001));
+        assert_eq!((stats.metadata_entries, stats.max_metadata), (1001, 7));
+    }
+
+    // A reader that produces its input one chunk at a time, so the whole input
+    // never exists in memory.
+    struct Generated<I> {
+        chunks: I,
+        chunk: Vec<u8>,
+        pos: usize,
+    }
+
+    impl<I: Iterator<Item = String>> Read for Generated<I> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            while self.pos == self.chunk.len() {
+                match self.chunks.next() {
+                    Some(chunk) => self.chunk = chunk.into_bytes(),
+                    None => return Ok(0),
+                }
+                self.pos = 0;
+            }
+
+            let len = out.len().min(self.chunk.len() - self.pos);
+            out[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+            self.pos += len;
+
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn tokens_split_across_buffers() {
+        for capacity in 1..6 {
+            let reader = BufReader::with_capacity(capacity, EXAMPLE.as_bytes());
+            assert_eq!(complex_sum(&parse_stream(reader, false).unwrap()), 66);
+        }
+    }
+
+    #[test]
+    fn large_generated_input() {
+        // 2 x 255 x 150 leaves of 255 metadata entries worth 255 each: about
+        // 20M tokens, summing past u32::MAX
+        let leaf = format!("0 255{}\n", " 255".repeat(255));
+        let chunks = iter::once("2 0 ".to_string()).chain((0..2).flat_map(move |_| {
+            let leaf = leaf.clone();
+            iter::once("255 0 ".to_string()).chain((0..255).flat_map(move |_| {
+                iter::once("150 0 ".to_string()).chain(iter::repeat_n(leaf.clone(), 150))
+            }))
+        }));
+        let reader = Generated {
+            chunks,
+            chunk: Vec::new(),
+            pos: 0,
+        };
+
+        let root = parse_stream(BufReader::new(reader), false).unwrap();
+        let leaves = 2 * 255 * 150;
+
+        assert_eq!(stats(&root).nodes, 1 + 2 + 2 * 255 + leaves);
+        assert_eq!(simple_sum(&root), leaves as u64 * 255 * 255);
+        assert!(simple_sum(&root) > u64::from(u32::MAX));
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_tree(state: &mut u64, depth: usize, out: &mut Vec<String>) {
+        let children = if depth < 6 { xorshift(state) % 4 } else { 0 };
+        let metadata = xorshift(state) % 6;
+        out.push(children.to_string());
+        out.push(metadata.to_string());
+
+        for _ in 0..children {
+            random_tree(state, depth + 1, out);
+        }
+
+        for _ in 0..metadata {
+            out.push((xorshift(state) % 256).to_string());
+        }
+    }
+
+    #[test]
+    fn example_round_trip() {
+        assert_eq!(to_license(&parse(EXAMPLE).unwrap()), EXAMPLE);
+    }
+
+    #[test]
+    fn random_round_trip() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+
+        for _ in 0..500 {
+            let mut tokens = Vec::new();
+            random_tree(&mut state, 0, &mut tokens);
+            let license = tokens.join(" ");
+
+            let tree = parse(&license).unwrap();
+            assert_eq!(to_license(&tree), license);
+            assert_eq!(parse(&to_license(&tree)).unwrap(), tree);
+        }
+    }
+
+    #[test]
+    fn one_extra_token() {
+        let input = format!("{} 5", EXAMPLE);
+
+        match parse(&input) {
+            Err(Error::TrailingData { remaining: 1 }) => (),
+            result => panic!("expected one trailing number, got {:?}", result),
+        }
+
+        assert_eq!(
+            parse_stream(Cursor::new(input), true).unwrap(),
+            parse(EXAMPLE).unwrap()
+        );
+    }
+
+    #[test]
+    fn second_tree_appended() {
+        let input = format!("{}\n{}\n", EXAMPLE, EXAMPLE);
+
+        match parse(&input) {
+            Err(Error::TrailingData { remaining: 16 }) => (),
+            result => panic!("expected 16 trailing numbers, got {:?}", result),
+        }
+
+        let root = parse_stream(Cursor::new(input), true).unwrap();
+        assert_eq!(simple_sum(&root), 138);
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            parse("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1"),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -1,70 +1,156 @@
+use std::collections::VecDeque;
+use std::env;
 use std::fmt;
 use std::fs::File;
 use std::io::{prelude::*, Error as IoError};
-use std::iter;
 use std::mem;
 use std::path::Path;
+use std::time::Instant;
 
 use regex::Regex;
 
 struct Marble {
-    next: usize,
-    prev: usize,
+    value: u32,
+    next: u32,
+    prev: u32,
 }
 
 struct Circle {
     marbles: Vec<Marble>,
-    current: usize,
+    free: Vec<u32>,
+    current: u32,
+    last: u32,
+}
+
+pub struct Turn {
+    pub marble: usize,
+    pub player: usize,
+    pub score: u64,
+    pub current: usize,
+}
+
+pub struct Turns<'a> {
+    circle: &'a mut Circle,
+    players: usize,
+}
+
+impl<'a> Iterator for Turns<'a> {
+    type Item = Turn;
+
+    fn next(&mut self) -> Option<Turn> {
+        let (marble, score) = self.circle.play();
+
+        Some(Turn {
+            marble,
+            player: (marble - 1) % self.players + 1,
+            score,
+            current: self.circle.marbles[self.circle.current as usize].value as usize,
+        })
+    }
 }
 
 impl Circle {
-    fn new(marbles: usize) -> Self {
-        let mut marbles = Vec::with_capacity(marbles);
+    fn new(marbles: usize) -> Result<Self, Error> {
+        // every slot index has to fit in a u32 link
+        if marbles >= u32::MAX as usize {
+            return Err(Error::Overflow);
+        }
+
+        let mut marbles = Vec::with_capacity(marbles - marbles / 23 * 2 + 2);
 
-        marbles.push(Marble { next: 0, prev: 0 });
+        marbles.push(Marble {
+            value: 0,
+            next: 0,
+            prev: 0,
+        });
 
-        Circle {
+        Ok(Circle {
             marbles,
+            free: Vec::new(),
             current: 0,
+            last: 0,
+        })
+    }
+
+    pub fn turns(&mut self, players: usize) -> Turns<'_> {
+        Turns {
+            circle: self,
+            players,
         }
     }
 
-    fn play(&mut self) -> (usize, usize) {
-        let new = self.marbles.len();
+    pub fn play(&mut self) -> (usize, u64) {
+        self.last += 1;
+        let new = self.last;
 
-        if new % 23 == 0 {
-            self.marbles.push(Marble { next: 0, prev: 0 });
-            (new, new + self.remove())
+        if new.is_multiple_of(23) {
+            (new as usize, u64::from(new) + u64::from(self.remove()))
         } else {
             self.insert(new);
 
-            (new, 0)
+            (new as usize, 0)
         }
     }
 
-    fn insert(&mut self, new: usize) {
-        let prev = self.marbles[self.current].next;
+    fn insert(&mut self, value: u32) {
+        let prev = self.marbles[self.current as usize].next;
+        let new = match self.free.pop() {
+            Some(slot) => slot,
+            None => self.marbles.len() as u32,
+        };
 
-        let next = mem::replace(&mut self.marbles[prev].next, new);
+        let next = mem::replace(&mut self.marbles[prev as usize].next, new);
+        let marble = Marble { value, next, prev };
 
-        self.marbles.push(Marble { next, prev });
+        match self.marbles.get_mut(new as usize) {
+            Some(slot) => *slot = marble,
+            None => self.marbles.push(marble),
+        }
 
-        self.marbles[next].prev = new;
+        self.marbles[next as usize].prev = new;
 
         self.current = new;
     }
 
-    fn remove(&mut self) -> usize {
-        let removed = (0..7).fold(self.current, |x, _| self.marbles[x].prev);
+    fn remove(&mut self) -> u32 {
+        let removed = (0..7).fold(self.current, |x, _| self.marbles[x as usize].prev);
 
-        let Marble { next, prev } = self.marbles[removed];
+        let Marble { value, next, prev } = self.marbles[removed as usize];
 
-        self.marbles[next].prev = prev;
-        self.marbles[prev].next = next;
+        self.marbles[next as usize].prev = prev;
+        self.marbles[prev as usize].next = next;
 
         self.current = next;
+        self.free.push(removed);
+
+        value
+    }
+
+    // Following `next` from the current marble has to visit every live marble
+    // exactly once, with matching `prev` links, and none of the free slots.
+    #[cfg(test)]
+    fn validate(&self) -> bool {
+        let live = self.last as usize + 1 - self.last as usize / 23 * 2;
+        let mut seen = vec![false; self.marbles.len()];
+        let mut slot = self.current;
+
+        if self.marbles.len() - self.free.len() != live {
+            return false;
+        }
+
+        for _ in 0..live {
+            let next = self.marbles[slot as usize].next;
+
+            if mem::replace(&mut seen[slot as usize], true)
+                || self.marbles[next as usize].prev != slot
+            {
+                return false;
+            }
+
+            slot = next;
+        }
 
-        removed
+        slot == self.current && self.free.iter().all(|&slot| !seen[slot as usize])
     }
 }
 
@@ -72,6 +158,9 @@ impl Circle {
 enum Error {
     Io(IoError),
     Invalid,
+    InvalidLine(usize, String),
+    Overflow,
+    Usage(String),
 }
 
 impl From<IoError> for Error {
@@ -85,50 +174,500 @@ impl fmt::Display for Error {
         match self {
             Error::Io(e) => fmt::Display::fmt(e, f),
             Error::Invalid => write!(f, "invalid input"),
+            Error::InvalidLine(line, s) => write!(f, "invalid game on line {}: {}", line, s),
+            Error::Overflow => write!(f, "marble count overflows"),
+            Error::Usage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+type Game = (usize, usize);
+
+struct Options {
+    game: Option<Game>,
+    bench: bool,
+    turns: Option<usize>,
+}
+
+fn parse_flag(flag: &str, value: Option<String>) -> Result<usize, Error> {
+    let value = value.ok_or_else(|| Error::Usage(format!("{} needs a value", flag)))?;
+
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value for {}: {}", flag, value)))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, Error> {
+    let mut args = args.into_iter();
+    let (mut players, mut marbles, mut turns) = (None, None, None);
+    let mut bench = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--players" => players = Some(parse_flag(&arg, args.next())?),
+            "--marbles" => marbles = Some(parse_flag(&arg, args.next())?),
+            "--bench" => bench = true,
+            "--turns" => turns = Some(parse_flag(&arg, args.next())?),
+            _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
         }
     }
+
+    let game = match (players, marbles) {
+        (Some(players), Some(marbles)) => Some((players, marbles)),
+        (None, None) => None,
+        _ => {
+            return Err(Error::Usage(
+                "--players and --marbles must be given together".to_string(),
+            ))
+        }
+    };
+
+    Ok(Options { game, bench, turns })
 }
 
-fn parse_input(path: &Path) -> Result<(usize, usize), Error> {
+fn parse_input(path: &Path) -> Result<Vec<(usize, Game)>, Error> {
     let s = &mut String::new();
 
     File::open(path)?.read_to_string(s)?;
 
-    let caps =
+    let re =
         Regex::new("(?P<players>[0-9]+) players; last marble is worth (?P<marbles>[0-9]+) points")
-            .unwrap()
-            .captures(s)
-            .ok_or(Error::Invalid)?;
+            .unwrap();
+
+    let games = s
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let invalid = || Error::InvalidLine(i + 1, line.to_string());
+            let caps = re.captures(line).ok_or_else(invalid)?;
+
+            Ok((
+                i + 1,
+                (
+                    caps["players"].parse().map_err(|_| invalid())?,
+                    caps["marbles"].parse().map_err(|_| invalid())?,
+                ),
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if games.is_empty() {
+        return Err(Error::Invalid);
+    }
+
+    Ok(games)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct GameResult {
+    winner: usize,
+    score: u64,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (elf {})", self.score, self.winner)
+    }
+}
+
+fn winner(scores: &[u64]) -> GameResult {
+    let (player, &score) = scores
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|&(_, score)| score)
+        .unwrap();
+
+    GameResult {
+        winner: player + 1,
+        score,
+    }
+}
 
-    Ok((
-        caps["players"].parse().unwrap(),
-        caps["marbles"].parse().unwrap(),
-    ))
+fn validate(players: usize, marbles: usize) -> Result<(), Error> {
+    if players == 0 || marbles == 0 {
+        return Err(Error::Invalid);
+    }
+
+    Ok(())
 }
 
-fn play(players: usize, marbles: usize) -> usize {
+fn play(players: usize, marbles: usize) -> Result<GameResult, Error> {
+    validate(players, marbles)?;
+
     let mut scores = vec![0; players];
-    let mut circle = Circle::new(marbles);
+    let mut circle = Circle::new(marbles)?;
 
-    iter::repeat_with(|| circle.play())
-        .take(marbles)
-        .map(|(marble, score)| {
-            let player = marble % players;
-            scores[player] += score;
+    for turn in circle.turns(players).take(marbles) {
+        scores[turn.player - 1] += turn.score;
+    }
 
-            scores[player]
-        })
-        .max()
-        .unwrap()
+    Ok(winner(&scores))
+}
+
+fn play_deque(players: usize, marbles: usize) -> Result<GameResult, Error> {
+    validate(players, marbles)?;
+
+    let mut scores = vec![0; players];
+    let mut circle = VecDeque::with_capacity(marbles + 1);
+
+    circle.push_back(0);
+
+    for marble in 1..=marbles {
+        if marble.is_multiple_of(23) {
+            circle.rotate_right(7);
+            scores[(marble - 1) % players] += (marble + circle.pop_back().unwrap()) as u64;
+            circle.rotate_left(1);
+        } else {
+            circle.rotate_left(1);
+            circle.push_back(marble);
+        }
+    }
+
+    Ok(winner(&scores))
+}
+
+fn bench(players: usize, marbles: usize) -> Result<(), Error> {
+    for (name, engine) in [("linked", play as fn(_, _) -> _), ("deque", play_deque)].iter() {
+        let start = Instant::now();
+        let result = engine(players, marbles)?;
+
+        println!("{:>6}: {} in {:?}", name, result, start.elapsed());
+    }
+
+    Ok(())
+}
+
+fn print_turns(players: usize, turns: usize) -> Result<(), Error> {
+    for turn in Circle::new(turns)?.turns(players).take(turns) {
+        println!(
+            "[{}] marble {:>3} current {:>3} score +{}",
+            turn.player, turn.marble, turn.current, turn.score
+        );
+    }
+
+    Ok(())
+}
+
+fn run(players: usize, marbles: usize, options: &Options) -> Result<(), Error> {
+    let part_two = marbles.checked_mul(100).ok_or(Error::Overflow)?;
+
+    println!("Part 1: {}", play_deque(players, marbles)?);
+    println!("Part 2: {}", play_deque(players, part_two)?);
+
+    if let Some(turns) = options.turns {
+        print_turns(players, turns)?;
+    }
+
+    if options.bench {
+        bench(players, marbles * 100)?;
+    }
+
+    Ok(())
 }
 
 fn main() -> Result<(), Error> {
     let path = Path::new("inputs/input-09-01.txt");
+    let options = parse_args(env::args().skip(1))?;
 
-    let (players, marbles) = parse_input(path)?;
+    if let Some((players, marbles)) = options.game {
+        return run(players, marbles, &options);
+    }
 
-    println!("Part 1: {}", play(players, marbles));
-    println!("Part 2: {}", play(players, marbles * 100));
+    for (line, (players, marbles)) in parse_input(path)? {
+        println!("Line {}: {} players, {} marbles", line, players, marbles);
+        run(players, marbles, &options)?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [(usize, usize, u64); 6] = [
+        (9, 25, 32),
+        (10, 1618, 8317),
+        (13, 7999, 146_373),
+        (17, 1104, 2764),
+        (21, 6111, 54718),
+        (30, 5807, 37305),
+    ];
+
+    #[test]
+    fn published_examples() {
+        for &(players, marbles, score) in &EXAMPLES {
+            assert_eq!(play(players, marbles).unwrap().score, score);
+            assert_eq!(play_deque(players, marbles).unwrap().score, score);
+        }
+    }
+
+    #[test]
+    fn scores_above_u32() {
+        let result = play(1, 1_000_000).unwrap();
+
+        assert!(result.score > u64::from(u32::MAX));
+        assert_eq!(result.score, 28_517_335_344);
+        assert_eq!(play_deque(1, 1_000_000).unwrap(), result);
+    }
+
+    #[test]
+    fn engines_agree() {
+        for players in 1..20 {
+            for marbles in 1..600 {
+                assert_eq!(
+                    play(players, marbles).unwrap(),
+                    play_deque(players, marbles).unwrap(),
+                    "{} players, {} marbles",
+                    players,
+                    marbles
+                );
+            }
+        }
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn random_games() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+
+        for _ in 0..200 {
+            let players = 1 + xorshift(&mut state) as usize % 49;
+            let marbles = 1 + xorshift(&mut state) as usize % 4999;
+            let game = format!("{} players, {} marbles", players, marbles);
+
+            assert_eq!(
+                play(players, marbles).unwrap(),
+                play_deque(players, marbles).unwrap(),
+                "{}",
+                game
+            );
+
+            let mut circle = Circle::new(marbles).unwrap();
+
+            for turn in 1..=marbles {
+                circle.play();
+
+                if turn % 1000 == 0 || turn == marbles {
+                    assert!(
+                        circle.validate(),
+                        "{}: broken links after turn {}",
+                        game,
+                        turn
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn validate_catches_broken_links() {
+        let mut circle = Circle::new(100).unwrap();
+        circle.turns(9).take(50).for_each(drop);
+        assert!(circle.validate());
+
+        let slot = circle.current as usize;
+        circle.marbles[slot].next = circle.marbles[slot].prev;
+        assert!(!circle.validate());
+    }
+
+    #[test]
+    fn part_two_overflow() {
+        let options = parse_args(Vec::new()).unwrap();
+
+        assert!(matches!(
+            run(9, usize::MAX / 50, &options),
+            Err(Error::Overflow)
+        ));
+    }
+
+    #[test]
+    fn walkthrough_turns() {
+        let mut circle = Circle::new(25).unwrap();
+        let turns: Vec<_> = circle.turns(9).take(25).collect();
+
+        for (i, turn) in turns.iter().enumerate() {
+            assert_eq!(turn.marble, i + 1);
+            assert_eq!(turn.player, i % 9 + 1);
+        }
+
+        let scoring: Vec<_> = turns.iter().filter(|turn| turn.score > 0).collect();
+        assert_eq!(scoring.len(), 1);
+        assert_eq!((scoring[0].marble, scoring[0].player), (23, 5));
+        assert_eq!((scoring[0].score, scoring[0].current), (32, 19));
+
+        assert_eq!(turns[0].current, 1);
+        assert_eq!(turns[24].current, 25);
+    }
+
+    #[test]
+    fn slots_are_recycled() {
+        for marbles in 1..2000 {
+            let mut circle = Circle::new(marbles).unwrap();
+            let capacity = circle.marbles.capacity();
+
+            for _ in circle.turns(7).take(marbles) {}
+
+            // marble 0 plus at most `marbles` more, and never a reallocation
+            assert!(circle.marbles.len() <= marbles + 1);
+            assert_eq!(circle.marbles.capacity(), capacity, "{} marbles", marbles);
+        }
+
+        for &(players, marbles, score) in &EXAMPLES {
+            let mut circle = Circle::new(marbles).unwrap();
+            let mut scores = vec![0; players];
+
+            for turn in circle.turns(players).take(marbles) {
+                scores[turn.player - 1] += turn.score;
+            }
+
+            assert_eq!(winner(&scores).score, score);
+            assert!(circle.marbles.len() <= marbles + 1);
+        }
+    }
+
+    #[test]
+    fn too_many_marbles() {
+        assert!(matches!(
+            Circle::new(u32::MAX as usize),
+            Err(Error::Overflow)
+        ));
+        assert!(matches!(
+            print_turns(9, 5_000_000_000),
+            Err(Error::Overflow)
+        ));
+        assert!(matches!(play(9, 5_000_000_000), Err(Error::Overflow)));
+    }
+
+    #[test]
+    fn winning_elf() {
+        let expected = GameResult {
+            winner: 5,
+            score: 32,
+        };
+
+        assert_eq!(play(9, 25).unwrap(), expected);
+        assert_eq!(play_deque(9, 25).unwrap(), expected);
+        assert_eq!(play(10, 1618).unwrap().winner, 10);
+
+        // ties go to the lowest-numbered elf
+        assert_eq!(winner(&[3, 7, 7]).winner, 2);
+    }
+
+    #[test]
+    fn degenerate_games() {
+        for &(players, marbles) in &[(0, 25), (9, 0), (0, 0)] {
+            assert!(matches!(play(players, marbles), Err(Error::Invalid)));
+            assert!(matches!(play_deque(players, marbles), Err(Error::Invalid)));
+        }
+
+        let single = GameResult {
+            winner: 1,
+            score: 0,
+        };
+        assert_eq!(play(3, 1).unwrap(), single);
+        assert_eq!(play_deque(3, 1).unwrap(), single);
+    }
+
+    fn parse_str(name: &str, input: &str) -> Result<Vec<(usize, Game)>, Error> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, input)?;
+
+        parse_input(&path)
+    }
+
+    #[test]
+    fn several_games() {
+        let input = "9 players; last marble is worth 25 points\n\
+                     \n\
+                     10 players; last marble is worth 1618 points\n\
+                     13 players; last marble is worth 7999 points\n";
+        let games = parse_str("aoc09-several-games.txt", input).unwrap();
+
+        assert_eq!(games, vec![(1, (9, 25)), (3, (10, 1618)), (4, (13, 7999))]);
+
+        let scores: Vec<_> = games
+            .iter()
+            .map(|&(_, (players, marbles))| play(players, marbles).unwrap().score)
+            .collect();
+        assert_eq!(scores, vec![32, 8317, 146_373]);
+    }
+
+    #[test]
+    fn invalid_game_line() {
+        let input = "9 players; last marble is worth 25 points\nnine players\n";
+
+        match parse_str("aoc09-invalid-game.txt", input) {
+            Err(Error::InvalidLine(2, line)) => assert_eq!(line, "nine players"),
+            result => panic!("expected an error on line 2, got {:?}", result),
+        }
+
+        assert!(matches!(
+            parse_str("aoc09-no-games.txt", "\n\n"),
+            Err(Error::Invalid)
+        ));
+    }
+
+    fn args(args: &[&str]) -> Result<Options, Error> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn game_from_arguments() {
+        let options = args(&["--players", "432", "--marbles", "7101900"]).unwrap();
+        assert_eq!(options.game, Some((432, 7_101_900)));
+
+        let options = args(&["--marbles", "25", "--players", "9", "--bench"]).unwrap();
+        assert_eq!(options.game, Some((9, 25)));
+        assert!(options.bench);
+
+        assert_eq!(args(&[]).unwrap().game, None);
+    }
+
+    #[test]
+    fn missing_arguments() {
+        for missing in &[
+            &["--players", "9"][..],
+            &["--marbles", "25"],
+            &["--players"],
+            &["--players", "9", "--marbles"],
+        ] {
+            assert!(
+                matches!(args(missing), Err(Error::Usage(_))),
+                "{:?}",
+                missing
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_arguments() {
+        for malformed in &[
+            &["--players", "nine", "--marbles", "25"][..],
+            &["--players", "-9", "--marbles", "25"],
+            &["--players", "9", "--marbles", "2.5"],
+            &["--elves", "9"],
+        ] {
+            assert!(
+                matches!(args(malformed), Err(Error::Usage(_))),
+                "{:?}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn last_marble_is_played() {
+        // marble 23 scores on the 23rd turn, so stopping one short loses it
+        assert_eq!(play(9, 23).unwrap().score, 32);
+        assert_eq!(play(9, 22).unwrap().score, 0);
+    }
+}
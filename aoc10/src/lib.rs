@@ -0,0 +1,828 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Error as IoError};
+use std::path::Path;
+
+use rayon::prelude::*;
+use regex::Regex;
+
+const GLYPH_WIDTH: usize = 6;
+const GLYPH_HEIGHT: usize = 10;
+const GLYPH_STRIDE: usize = 8;
+
+const MAX_DRAW_AREA: u64 = 1 << 24;
+
+// The message font only covers the letters that show up in real puzzle
+// outputs; there's no published bitmap for the rest (D, I, M, O, Q, S, T, U,
+// V, W, Y), so those fail to decode with a "not in the font" error.
+const FONT: [(char, u64); 15] = [
+    ('A', 0x31286187f861861),
+    ('B', 0xfa1861fa186187e),
+    ('C', 0x7a182082082085e),
+    ('E', 0xfe0820fa082083f),
+    ('F', 0xfe0820fa0820820),
+    ('G', 0x7a18208278618dd),
+    ('H', 0x861861fe1861861),
+    ('J', 0x1c20820820a289c),
+    ('K', 0x862928c30a248a1),
+    ('L', 0x82082082082083f),
+    ('N', 0x871c69a659638e1),
+    ('P', 0xfa1861fa0820820),
+    ('R', 0xfa1861fa48a2861),
+    ('X', 0x86149230c492861),
+    ('Z', 0xfc104210842083f),
+];
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Invalid,
+    FrameSize { width: usize, height: usize },
+    UnknownGlyph { start: usize, end: usize },
+    Mismatch { fast: i64, linear: i64 },
+    OutOfBounds { x: i64, y: i64 },
+    Usage(String),
+    EmptyInput,
+    FrameTooLarge { width: u64, height: u64 },
+    EdgesMismatch(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edges {
+    pub min_x: i64,
+    pub min_y: i64,
+    pub max_x: i64,
+    pub max_y: i64,
+}
+
+impl Edges {
+    fn point(position: &Position) -> Self {
+        Edges {
+            min_x: position.x,
+            min_y: position.y,
+            max_x: position.x,
+            max_y: position.y,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Edges {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn area(&self) -> (u64, u64) {
+        (
+            (self.max_x - self.min_x + 1) as u64,
+            (self.max_y - self.min_y + 1) as u64,
+        )
+    }
+}
+
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Position {
+    fn index(&self, edges: &Edges) -> Option<usize> {
+        if self.x < edges.min_x
+            || self.x > edges.max_x
+            || self.y < edges.min_y
+            || self.y > edges.max_y
+        {
+            return None;
+        }
+
+        let row = (edges.max_x - edges.min_x + 2) as usize;
+
+        let x = (self.x - edges.min_x) as usize;
+        let y = (self.y - edges.min_y) as usize;
+
+        Some(y * row + x)
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub struct Velocity {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub struct Signal {
+    pub position: Position,
+    pub velocity: Velocity,
+}
+
+impl Signal {
+    pub fn at(&self, time: i64) -> Position {
+        Position {
+            x: self.position.x + self.velocity.x * time,
+            y: self.position.y + self.velocity.y * time,
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid => write!(f, "invalid input"),
+            Error::FrameSize { width, height } => write!(
+                f,
+                "a {}x{} frame can't be split into {}x{} glyphs",
+                width, height, GLYPH_WIDTH, GLYPH_HEIGHT
+            ),
+            Error::UnknownGlyph { start, end } => {
+                write!(f, "glyph at columns {}..{} is not in the font", start, end)
+            }
+            Error::Mismatch { fast, linear } => write!(
+                f,
+                "ternary search found t = {}, linear scan found t = {}",
+                fast, linear
+            ),
+            Error::OutOfBounds { x, y } => {
+                write!(f, "point ({}, {}) lies outside the frame", x, y)
+            }
+            Error::Usage(e) => write!(f, "{}", e),
+            Error::EmptyInput => write!(f, "no signals in the input"),
+            Error::FrameTooLarge { width, height } => {
+                write!(f, "a {}x{} frame is too large to draw", width, height)
+            }
+            Error::EdgesMismatch(t) => {
+                write!(f, "parallel and serial edges differ at t = {}", t)
+            }
+        }
+    }
+}
+
+pub fn parse_input(path: &Path) -> Result<Vec<Signal>, Error> {
+    let re = Regex::new(
+        r"(?x)
+        position=<\s*
+        (?P<x>-?[0-9]+),\s+
+        (?P<y>-?[0-9]+)>\s
+        velocity=<\s*
+        (?P<v_x>-?[0-9]+),\s+
+        (?P<v_y>-?[0-9]+)>",
+    )
+    .unwrap();
+
+    BufReader::new(File::open(path)?)
+        .lines()
+        .map(|line| {
+            line.map_err(|e| e.into()).and_then(|s| {
+                re.captures(&s).ok_or(Error::Invalid).map(|cap| Signal {
+                    position: Position {
+                        x: cap["x"].parse().unwrap(),
+                        y: cap["y"].parse().unwrap(),
+                    },
+                    velocity: Velocity {
+                        x: cap["v_x"].parse().unwrap(),
+                        y: cap["v_y"].parse().unwrap(),
+                    },
+                })
+            })
+        })
+        .collect()
+}
+
+fn in_lockstep(signals: &[Signal]) -> Result<bool, Error> {
+    let first = signals.first().ok_or(Error::EmptyInput)?;
+
+    Ok(signals
+        .iter()
+        .all(|signal| signal.velocity == first.velocity))
+}
+
+pub fn find_min_area(signals: &[Signal]) -> Result<Vec<i64>, Error> {
+    if in_lockstep(signals)? {
+        return Ok(vec![0]);
+    }
+
+    let mut minima = vec![0];
+    let mut min_area = area(signals, 0)?;
+
+    for t in 1.. {
+        let area = area(signals, t)?;
+
+        if area > min_area {
+            break;
+        }
+
+        if area < min_area {
+            min_area = area;
+            minima.clear();
+        }
+
+        minima.push(t);
+    }
+
+    Ok(minima)
+}
+
+pub fn area(signals: &[Signal], time: i64) -> Result<u64, Error> {
+    let (row, col) = find_edges(signals, time)?.area();
+
+    Ok(row.saturating_mul(col))
+}
+
+fn find_bracket(signals: &[Signal]) -> Result<i64, Error> {
+    let start = find_edges(signals, 0)?;
+    let (min_vx, min_vy, max_vx, max_vy) = signals.iter().map(|signal| &signal.velocity).fold(
+        (i64::MAX, i64::MAX, i64::MIN, i64::MIN),
+        |(min_vx, min_vy, max_vx, max_vy), velocity| {
+            (
+                min_vx.min(velocity.x),
+                min_vy.min(velocity.y),
+                max_vx.max(velocity.x),
+                max_vy.max(velocity.y),
+            )
+        },
+    );
+
+    let bound = |span: i64, speed: i64| if speed > 0 { 2 * span / speed } else { 0 };
+
+    Ok(bound(start.max_x - start.min_x, max_vx - min_vx)
+        .max(bound(start.max_y - start.min_y, max_vy - min_vy))
+        + 1)
+}
+
+pub fn find_min_area_fast(signals: &[Signal]) -> Result<Vec<i64>, Error> {
+    if in_lockstep(signals)? {
+        return Ok(vec![0]);
+    }
+
+    let (mut lo, mut hi) = (0, find_bracket(signals)?);
+
+    while hi - lo > 2 {
+        let (m1, m2) = (lo + (hi - lo) / 3, hi - (hi - lo) / 3);
+        let (a1, a2) = (area(signals, m1)?, area(signals, m2)?);
+
+        if a1 <= a2 {
+            hi = m2;
+        }
+
+        if a1 >= a2 {
+            lo = m1;
+        }
+    }
+
+    let (mut min_area, mut first) = (area(signals, lo)?, lo);
+
+    for t in lo + 1..=hi {
+        let area = area(signals, t)?;
+
+        if area < min_area {
+            min_area = area;
+            first = t;
+        }
+    }
+
+    let mut last = first;
+
+    while first > 0 && area(signals, first - 1)? == min_area {
+        first -= 1;
+    }
+
+    while area(signals, last + 1)? == min_area {
+        last += 1;
+    }
+
+    Ok((first..=last).collect())
+}
+
+pub fn positions(signals: &[Signal], time: i64) -> Vec<Position> {
+    signals.par_iter().map(|signal| signal.at(time)).collect()
+}
+
+pub fn bounds(positions: &[Position]) -> Result<Edges, Error> {
+    positions
+        .par_iter()
+        .map(Edges::point)
+        .reduce_with(Edges::merge)
+        .ok_or(Error::EmptyInput)
+}
+
+pub fn find_edges(signals: &[Signal], time: i64) -> Result<Edges, Error> {
+    signals
+        .par_iter()
+        .map(|signal| Edges::point(&signal.at(time)))
+        .reduce_with(Edges::merge)
+        .ok_or(Error::EmptyInput)
+}
+
+pub fn find_edges_serial(signals: &[Signal], time: i64) -> Result<Edges, Error> {
+    let mut positions = signals.iter().map(|signal| signal.at(time));
+    let first = positions.next().ok_or(Error::EmptyInput)?;
+
+    let (min_x, min_y, max_x, max_y) = positions.fold(
+        (first.x, first.y, first.x, first.y),
+        |(min_x, min_y, max_x, max_y), Position { x, y }| {
+            (
+                if x < min_x { x } else { min_x },
+                if y < min_y { y } else { min_y },
+                if x > max_x { x } else { max_x },
+                if y > max_y { y } else { max_y },
+            )
+        },
+    );
+
+    Ok(Edges {
+        min_x,
+        min_y,
+        max_x,
+        max_y,
+    })
+}
+
+pub fn draw(signals: &[Signal], time: i64, edges: &Edges, clip: bool) -> Result<String, Error> {
+    draw_positions(&positions(signals, time), edges, clip)
+}
+
+pub fn draw_positions(positions: &[Position], edges: &Edges, clip: bool) -> Result<String, Error> {
+    let (row, col) = edges.area();
+
+    if (row + 1).saturating_mul(col) > MAX_DRAW_AREA {
+        return Err(Error::FrameTooLarge {
+            width: row,
+            height: col,
+        });
+    }
+
+    let (row, col) = (row as usize, col as usize);
+    let mut v = Vec::with_capacity((row + 1) * col);
+
+    for _ in 0..col {
+        v.resize(v.len() + row, b'.');
+        v.push(b'\n');
+    }
+
+    for position in positions {
+        match position.index(edges) {
+            Some(index) => v[index] = b'#',
+            None if clip => {}
+            None => {
+                return Err(Error::OutOfBounds {
+                    x: position.x,
+                    y: position.y,
+                })
+            }
+        }
+    }
+
+    Ok(String::from_utf8(v).unwrap())
+}
+
+pub fn decode(frame: &str) -> Result<String, Error> {
+    let rows: Vec<_> = frame.lines().map(str::as_bytes).collect();
+    let (width, height) = (rows.first().map_or(0, |row| row.len()), rows.len());
+    let padded = width + GLYPH_STRIDE - GLYPH_WIDTH;
+
+    if height != GLYPH_HEIGHT || !padded.is_multiple_of(GLYPH_STRIDE) {
+        return Err(Error::FrameSize { width, height });
+    }
+
+    (0..padded / GLYPH_STRIDE)
+        .map(|i| {
+            let (start, end) = (i * GLYPH_STRIDE, i * GLYPH_STRIDE + GLYPH_WIDTH);
+            let glyph = rows.iter().try_fold(0, |glyph, row| {
+                row.get(start..end)
+                    .ok_or(Error::FrameSize { width, height })
+                    .map(|row| {
+                        row.iter()
+                            .fold(glyph, |glyph, &c| glyph << 1 | u64::from(c == b'#'))
+                    })
+            })?;
+
+            FONT.iter()
+                .find(|(_, bitmap)| *bitmap == glyph)
+                .map(|&(c, _)| c)
+                .ok_or(Error::UnknownGlyph { start, end })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: [&str; GLYPH_HEIGHT] = [
+        "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#",
+        "#....#",
+    ];
+    const Z: [&str; GLYPH_HEIGHT] = [
+        "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....",
+        "######",
+    ];
+    const I: [&str; GLYPH_HEIGHT] = [
+        "######", "..##..", "..##..", "..##..", "..##..", "..##..", "..##..", "..##..", "..##..",
+        "######",
+    ];
+
+    fn frame(glyphs: &[[&str; GLYPH_HEIGHT]]) -> String {
+        (0..GLYPH_HEIGHT)
+            .map(|y| {
+                let row: Vec<_> = glyphs.iter().map(|glyph| glyph[y]).collect();
+                format!("{}\n", row.join(".."))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hand_assembled_frame() {
+        assert_eq!(decode(&frame(&[H, Z])).unwrap(), "HZ");
+        assert_eq!(decode(&frame(&[Z])).unwrap(), "Z");
+    }
+
+    #[test]
+    fn every_glyph_decodes() {
+        for &(c, bitmap) in &FONT {
+            let art: String = (0..GLYPH_HEIGHT)
+                .map(|y| {
+                    let row: String = (0..GLYPH_WIDTH)
+                        .map(|x| {
+                            let bit = GLYPH_WIDTH * GLYPH_HEIGHT - 1 - (y * GLYPH_WIDTH + x);
+                            if bitmap >> bit & 1 == 1 {
+                                '#'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    row + "\n"
+                })
+                .collect();
+
+            assert_eq!(decode(&art).unwrap(), c.to_string());
+        }
+    }
+
+    #[test]
+    fn unknown_glyph_columns() {
+        match decode(&frame(&[H, I])) {
+            Err(Error::UnknownGlyph { start: 8, end: 14 }) => (),
+            result => panic!("expected an unknown glyph in 8..14, got {:?}", result),
+        }
+
+        let error = decode(&frame(&[H, I])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "glyph at columns 8..14 is not in the font"
+        );
+    }
+
+    #[test]
+    fn frame_size() {
+        let short: String = frame(&[H])
+            .lines()
+            .skip(1)
+            .map(|row| row.to_string() + "\n")
+            .collect();
+        assert!(matches!(
+            decode(&short),
+            Err(Error::FrameSize {
+                width: 6,
+                height: 9
+            })
+        ));
+
+        let wide: String = frame(&[H])
+            .lines()
+            .map(|row| row.to_string() + ".\n")
+            .collect();
+        assert!(matches!(
+            decode(&wide),
+            Err(Error::FrameSize {
+                width: 7,
+                height: 10
+            })
+        ));
+    }
+
+    const EXAMPLE: &str = "\
+position=< 9,  1> velocity=< 0,  2>
+position=< 7,  0> velocity=<-1,  0>
+position=< 3, -2> velocity=<-1,  1>
+position=< 6, 10> velocity=<-2, -1>
+position=< 2, -4> velocity=< 2,  2>
+position=<-6, 10> velocity=< 2, -2>
+position=< 1,  8> velocity=< 1, -1>
+position=< 1,  7> velocity=< 1,  0>
+position=<-3, 11> velocity=< 1, -2>
+position=< 7,  6> velocity=<-1, -1>
+position=<-2,  3> velocity=< 1,  0>
+position=<-4,  3> velocity=< 2,  0>
+position=<10, -3> velocity=<-1,  1>
+position=< 5, 11> velocity=< 1, -2>
+position=< 4,  7> velocity=< 0, -1>
+position=< 8, -2> velocity=< 0,  1>
+position=<15,  0> velocity=<-2,  0>
+position=< 1,  6> velocity=< 1,  0>
+position=< 8,  9> velocity=< 0, -1>
+position=< 3,  3> velocity=<-1,  1>
+position=< 0,  5> velocity=< 0, -1>
+position=<-2,  2> velocity=< 2,  0>
+position=< 5, -2> velocity=< 1,  2>
+position=< 1,  4> velocity=< 2,  1>
+position=<-2,  7> velocity=< 2, -2>
+position=< 3,  6> velocity=<-1, -1>
+position=< 5,  0> velocity=< 1,  0>
+position=<-6,  0> velocity=< 2,  0>
+position=< 5,  9> velocity=< 1, -2>
+position=<14,  7> velocity=<-2,  0>
+position=<-3,  6> velocity=< 2, -1>
+";
+
+    const HI: &str = "\
+#...#..###
+#...#...#.
+#...#...#.
+#####...#.
+#...#...#.
+#...#...#.
+#...#...#.
+#...#..###
+";
+
+    fn signal(x: i64, y: i64, v_x: i64, v_y: i64) -> Signal {
+        Signal {
+            position: Position { x, y },
+            velocity: Velocity { x: v_x, y: v_y },
+        }
+    }
+
+    fn parse(name: &str, input: &str) -> Result<Vec<Signal>, Error> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, input)?;
+
+        parse_input(&path)
+    }
+
+    fn example() -> Vec<Signal> {
+        parse("aoc10-example.txt", EXAMPLE).unwrap()
+    }
+
+    // The example, rewound so that it converges at `time` instead of 3.
+    fn converging_at(time: i64) -> Vec<Signal> {
+        example()
+            .iter()
+            .map(|s| {
+                let Position { x, y } = s.at(3 - time);
+                signal(x, y, s.velocity.x, s.velocity.y)
+            })
+            .collect()
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // Non-increasing, then non-decreasing: the only shape either search is
+    // expected to get right.
+    fn unimodal(areas: &[u64]) -> bool {
+        let rising = areas.windows(2).position(|w| w[1] > w[0]);
+
+        rising.is_none_or(|i| areas[i..].windows(2).all(|w| w[1] >= w[0]))
+    }
+
+    #[test]
+    fn fast_matches_linear() {
+        let mut state = 0x853c_49e6_748f_ea9b;
+        let mut random = |n: u64| xorshift(&mut state) % n;
+        let mut compared = 0;
+
+        for _ in 0..300 {
+            let time = random(500) as i64;
+            let signals: Vec<_> = (0..2 + random(40))
+                .map(|_| {
+                    let (v_x, v_y) = (random(7) as i64 - 3, random(7) as i64 - 3);
+                    let (x, y) = (random(21) as i64 - 10, random(11) as i64 - 5);
+                    signal(x - v_x * time, y - v_y * time, v_x, v_y)
+                })
+                .collect();
+            let areas: Vec<_> = (0..2 * time + 20)
+                .map(|t| area(&signals, t).unwrap())
+                .collect();
+
+            if in_lockstep(&signals).unwrap() || !unimodal(&areas) {
+                continue;
+            }
+
+            assert_eq!(
+                find_min_area_fast(&signals).unwrap(),
+                find_min_area(&signals).unwrap()
+            );
+            compared += 1;
+        }
+
+        assert!(compared > 200, "only {} unimodal samples", compared);
+    }
+
+    #[test]
+    fn far_convergence() {
+        let signals = converging_at(1_000_000);
+        let minima = find_min_area_fast(&signals).unwrap();
+
+        assert_eq!(minima, vec![1_000_000]);
+        let edges = find_edges(&signals, minima[0]).unwrap();
+        assert_eq!(draw(&signals, minima[0], &edges, false).unwrap(), HI);
+    }
+
+    // Five rows tall throughout; the width goes 20, 18, 18, 19, 20, ...
+    fn plateau() -> Vec<Signal> {
+        vec![
+            signal(0, 0, 0, 0),
+            signal(19, 0, -2, 0),
+            signal(17, 0, 0, 0),
+            signal(15, 4, 1, 0),
+        ]
+    }
+
+    #[test]
+    fn plateau_areas() {
+        let areas: Vec<_> = (0..6).map(|t| area(&plateau(), t).unwrap()).collect();
+
+        assert_eq!(areas, [100, 90, 90, 95, 100, 105]);
+    }
+
+    #[test]
+    fn plateau_returns_every_tied_time() {
+        assert_eq!(find_min_area(&plateau()).unwrap(), [1, 2]);
+        assert_eq!(find_min_area_fast(&plateau()).unwrap(), [1, 2]);
+    }
+
+    fn stale() -> (Vec<Signal>, Edges) {
+        let signals = vec![signal(0, 0, 1, 1), signal(3, 0, 0, 0)];
+        let edges = find_edges(&signals, 0).unwrap();
+
+        (signals, edges)
+    }
+
+    #[test]
+    fn stale_edges_name_the_point() {
+        let (signals, edges) = stale();
+        let error = draw(&signals, 5, &edges, false).unwrap_err();
+
+        assert!(matches!(error, Error::OutOfBounds { x: 5, y: 5 }));
+        assert!(error.to_string().contains("(5, 5)"), "{}", error);
+    }
+
+    #[test]
+    fn stale_edges_clipped() {
+        let (signals, edges) = stale();
+
+        assert_eq!(draw(&signals, 5, &edges, true).unwrap(), "...#\n");
+    }
+
+    #[test]
+    fn index_outside_edges() {
+        let edges = Edges {
+            min_x: -1,
+            min_y: -1,
+            max_x: 1,
+            max_y: 1,
+        };
+
+        assert_eq!(Position { x: -1, y: -1 }.index(&edges), Some(0));
+        assert_eq!(Position { x: 1, y: 1 }.index(&edges), Some(10));
+        assert_eq!(Position { x: 2, y: 0 }.index(&edges), None);
+        assert_eq!(Position { x: 0, y: -2 }.index(&edges), None);
+    }
+
+    #[test]
+    fn empty_input() {
+        let signals = parse("aoc10-empty.txt", "").unwrap();
+
+        assert!(signals.is_empty());
+        assert!(matches!(find_min_area(&signals), Err(Error::EmptyInput)));
+        assert!(matches!(
+            find_min_area_fast(&signals),
+            Err(Error::EmptyInput)
+        ));
+        assert!(matches!(find_edges(&signals, 0), Err(Error::EmptyInput)));
+        assert!(matches!(
+            find_edges_serial(&signals, 0),
+            Err(Error::EmptyInput)
+        ));
+    }
+
+    #[test]
+    fn single_point() {
+        let signals = vec![signal(7, -3, 2, 5)];
+
+        assert_eq!(find_min_area(&signals).unwrap(), [0]);
+        assert_eq!(find_min_area_fast(&signals).unwrap(), [0]);
+        let edges = find_edges(&signals, 0).unwrap();
+        assert_eq!(draw(&signals, 0, &edges, false).unwrap(), "#\n");
+    }
+
+    #[test]
+    fn beyond_i32() {
+        let time = 2_000_000_000;
+        let signals = converging_at(time);
+
+        assert!(signals
+            .iter()
+            .any(|s| s.position.x.abs() > i64::from(i32::MAX)));
+        assert_eq!(find_min_area_fast(&signals).unwrap(), [time]);
+        assert_eq!(find_edges(&signals, time).unwrap().area(), (10, 8));
+        let edges = find_edges(&signals, time).unwrap();
+        assert_eq!(draw(&signals, time, &edges, false).unwrap(), HI);
+    }
+
+    #[test]
+    fn huge_frame_is_refused() {
+        let signals = converging_at(2_000_000_000);
+        let edges = find_edges(&signals, 0).unwrap();
+
+        assert!(matches!(
+            draw(&signals, 0, &edges, false),
+            Err(Error::FrameTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn example_converges() {
+        let signals = example();
+
+        assert_eq!(signals.len(), 31);
+        assert_eq!(find_min_area(&signals).unwrap(), [3]);
+        assert_eq!(find_min_area_fast(&signals).unwrap(), [3]);
+        let edges = find_edges(&signals, 3).unwrap();
+        assert_eq!(edges.area(), (10, 8));
+        assert_eq!(draw(&signals, 3, &edges, false).unwrap(), HI);
+    }
+
+    #[test]
+    fn padded_fields() {
+        let signals = parse(
+            "aoc10-padding.txt",
+            "position=< 9,  1> velocity=< 0,  2>\n\
+             position=<-6, 10> velocity=< 2, -2>\n\
+             position=<-52044,  10582> velocity=< 5, -1>\n",
+        )
+        .unwrap();
+        let fields: Vec<_> = signals
+            .iter()
+            .map(|s| (s.position.x, s.position.y, s.velocity.x, s.velocity.y))
+            .collect();
+
+        assert_eq!(
+            fields,
+            [(9, 1, 0, 2), (-6, 10, 2, -2), (-52044, 10582, 5, -1)]
+        );
+    }
+
+    #[test]
+    fn malformed_line() {
+        assert!(matches!(
+            parse("aoc10-malformed.txt", "position=<1, 2> velocity=<3>\n"),
+            Err(Error::Invalid)
+        ));
+    }
+
+    #[test]
+    fn parallel_edges_match_serial() {
+        let mut state = 12345;
+        let mut random = |n: u64| (xorshift(&mut state) % (2 * n + 1)) as i64 - n as i64;
+        let signals: Vec<_> = (0..2_000_000)
+            .map(|_| signal(random(1_000_000), random(1_000_000), random(4), random(4)))
+            .collect();
+
+        for &t in &[0, 7, 1000, -250] {
+            assert_eq!(
+                find_edges(&signals, t).unwrap(),
+                find_edges_serial(&signals, t).unwrap()
+            );
+        }
+
+        let positions = positions(&signals, 7);
+        assert_eq!(
+            bounds(&positions).unwrap(),
+            find_edges_serial(&signals, 7).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn real_input_message() {
+        let signals = parse_input(Path::new("../inputs/input-10-01.txt")).unwrap();
+        let t = find_min_area(&signals).unwrap()[0];
+        let frame = draw(&signals, t, &find_edges(&signals, t).unwrap(), false).unwrap();
+
+        assert_eq!(decode(&frame).unwrap(), "RBCZAEPP");
+    }
+}
@@ -1,186 +1,219 @@
-use std::fmt;
+use aoc10::{
+    bounds, decode, draw, draw_positions, find_edges, find_edges_serial, find_min_area,
+    find_min_area_fast, parse_input, positions, Edges, Error, Position, Signal,
+};
+use std::env;
 use std::fs::File;
-use std::io::{prelude::*, BufReader, Error as IoError};
+use std::io::prelude::*;
 use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
-use regex::Regex;
+const MAX_FRAME_AREA: u64 = 100_000;
+const FRAME_DELAY: Duration = Duration::from_millis(500);
 
-#[derive(Debug)]
-enum Error {
-    Io(IoError),
-    Invalid,
-}
+fn render_svg(signals: &[Signal], time: i64, edges: &Edges) -> String {
+    let (row, col) = edges.area();
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 {0} {1}\">\n\
+         <rect x=\"-1\" y=\"-1\" width=\"{0}\" height=\"{1}\" fill=\"#0f0f23\"/>\n",
+        row + 2,
+        col + 2
+    );
 
-struct Edges {
-    min_x: i32,
-    min_y: i32,
-    max_x: i32,
-    max_y: i32,
-}
+    for signal in signals {
+        let Position { x, y } = signal.at(time);
 
-impl Edges {
-    fn area(&self) -> (usize, usize) {
-        (
-            (self.max_x - self.min_x + 1) as usize,
-            (self.max_y - self.min_y + 1) as usize,
-        )
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#ffff66\"/>\n",
+            x - edges.min_x,
+            y - edges.min_y
+        ));
     }
-}
 
-struct Position {
-    x: i32,
-    y: i32,
+    svg.push_str("</svg>\n");
+    svg
 }
 
-impl Position {
-    fn index(&self, edges: &Edges) -> usize {
-        let row = (edges.max_x - edges.min_x + 2) as usize;
-
-        let x = (self.x - edges.min_x) as usize;
-        let y = (self.y - edges.min_y) as usize;
+fn frame(signals: &[Signal], time: i64) -> Result<Option<String>, Error> {
+    let edges = find_edges(signals, time)?;
+    let (row, col) = edges.area();
 
-        y * row + x
+    if row.saturating_mul(col) > MAX_FRAME_AREA {
+        return Ok(None);
     }
-}
 
-struct Velocity {
-    x: i32,
-    y: i32,
+    draw(signals, time, &edges, false).map(Some)
 }
 
-struct Signal {
-    position: Position,
-    velocity: Velocity,
+fn frames(signals: &[Signal], time: i64, n: i64) -> Result<Vec<(i64, Option<String>)>, Error> {
+    (time - n..=time + n)
+        .map(|t| frame(signals, t).map(|frame| (t, frame)))
+        .collect()
 }
 
-impl Signal {
-    fn at(&self, time: i32) -> Position {
-        Position {
-            x: self.position.x + self.velocity.x * time,
-            y: self.position.y + self.velocity.y * time,
+fn animate(signals: &[Signal], time: i64, n: i64) -> Result<(), Error> {
+    for (t, frame) in frames(signals, time, n)? {
+        println!("t = {}", t);
+
+        match frame {
+            Some(frame) => print!("{}", frame),
+            None => println!("(frame larger than {} cells, skipped)", MAX_FRAME_AREA),
         }
-    }
-}
 
-impl From<IoError> for Error {
-    fn from(error: IoError) -> Self {
-        Error::Io(error)
+        thread::sleep(FRAME_DELAY);
     }
+
+    Ok(())
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Error::Io(e) => fmt::Display::fmt(e, f),
-            Error::Invalid => write!(f, "invalid input"),
+fn render(signals: &[Signal], minima: &[i64]) -> Result<(), Error> {
+    println!("Part 1:");
+
+    for &t in minima {
+        let positions = positions(signals, t);
+        let frame = draw_positions(&positions, &bounds(&positions)?, false)?;
+
+        if minima.len() > 1 {
+            println!("t = {}", t);
+        }
+
+        print!("{}", frame);
+
+        match decode(&frame) {
+            Ok(message) => println!("Message: {}", message),
+            Err(e) => println!("Message: {}", e),
         }
     }
-}
 
-fn parse_input(path: &Path) -> Result<Vec<Signal>, Error> {
-    let re = Regex::new(
-        r"(?x)
-        position=<\s*
-        (?P<x>-?[0-9]+),\s+
-        (?P<y>-?[0-9]+)>\s
-        velocity=<\s*
-        (?P<v_x>-?[0-9]+),\s+
-        (?P<v_y>-?[0-9]+)>",
-    )
-    .unwrap();
-
-    BufReader::new(File::open(path)?)
-        .lines()
-        .map(|line| {
-            line.map_err(|e| e.into()).and_then(|s| {
-                re.captures(&s).ok_or(Error::Invalid).map(|cap| Signal {
-                    position: Position {
-                        x: cap["x"].parse().unwrap(),
-                        y: cap["y"].parse().unwrap(),
-                    },
-                    velocity: Velocity {
-                        x: cap["v_x"].parse().unwrap(),
-                        y: cap["v_y"].parse().unwrap(),
-                    },
-                })
-            })
-        })
-        .collect()
+    Ok(())
 }
 
-fn find_min_area(signals: &[Signal]) -> (i32, Edges) {
-    (0..)
-        .scan(!0, |area_prev, t| {
-            let edges = find_edges(signals, t);
-            let (row, col) = edges.area();
-            let area = row * col;
-
-            if area < *area_prev {
-                *area_prev = area;
-                Some((t, edges))
-            } else {
-                None
-            }
-        })
-        .last()
-        .unwrap()
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Result<Option<&'a String>, Error> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => args
+            .get(i + 1)
+            .map(Some)
+            .ok_or_else(|| Error::Usage(format!("{} needs a value", flag))),
+        None => Ok(None),
+    }
 }
 
-fn find_edges(signals: &[Signal], time: i32) -> Edges {
-    let (min_x, min_y, max_x, max_y) = signals.iter().map(|signal| signal.at(time)).fold(
-        (
-            i32::max_value(),
-            i32::max_value(),
-            i32::min_value(),
-            i32::min_value(),
+fn main() -> Result<(), Error> {
+    let path = Path::new("inputs/input-10-01.txt");
+
+    let args: Vec<_> = env::args().skip(1).collect();
+    let svg = flag_value(&args, "--svg")?;
+    let animate_n = match flag_value(&args, "--animate")? {
+        Some(n) => Some(
+            n.parse()
+                .map_err(|_| Error::Usage(format!("invalid value for --animate: {}", n)))?,
         ),
-        |(min_x, min_y, max_x, max_y), Position { x, y }| {
-            (
-                if x < min_x { x } else { min_x },
-                if y < min_y { y } else { min_y },
-                if x > max_x { x } else { max_x },
-                if y > max_y { y } else { max_y },
-            )
-        },
-    );
+        None => None,
+    };
 
-    Edges {
-        min_x,
-        min_y,
-        max_x,
-        max_y,
-    }
-}
+    let signals = parse_input(path)?;
 
-fn draw(signals: &[Signal], time: i32, edges: &Edges) -> String {
-    let (row, col) = edges.area();
+    let minima = find_min_area_fast(&signals)?;
 
-    let mut v = Vec::with_capacity((row + 1) * col);
+    if args.iter().any(|arg| arg == "--check") {
+        let linear = find_min_area(&signals)?;
 
-    for _ in 0..col {
-        for _ in 0..row {
-            v.push(b'.');
+        if linear != minima {
+            return Err(Error::Mismatch {
+                fast: minima[0],
+                linear: linear[0],
+            });
         }
 
-        v.push(b'\n');
+        if find_edges(&signals, minima[0])? != find_edges_serial(&signals, minima[0])? {
+            return Err(Error::EdgesMismatch(minima[0]));
+        }
     }
 
-    for signal in signals {
-        v[signal.at(time).index(edges)] = b'#';
+    render(&signals, &minima)?;
+    println!("Part 2: {}", minima[0]);
+
+    if let Some(n) = animate_n {
+        animate(&signals, minima[0], n)?;
+    }
+
+    if let Some(svg) = svg {
+        let t = minima[0];
+
+        File::create(svg)?
+            .write_all(render_svg(&signals, t, &find_edges(&signals, t)?).as_bytes())?;
     }
 
-    String::from_utf8(v).unwrap()
+    Ok(())
 }
 
-fn main() -> Result<(), Error> {
-    let path = Path::new("inputs/input-10-01.txt");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc10::Velocity;
 
-    let signals = parse_input(path)?;
+    fn signal(x: i64, y: i64, v_x: i64, v_y: i64) -> Signal {
+        Signal {
+            position: Position { x, y },
+            velocity: Velocity { x: v_x, y: v_y },
+        }
+    }
+
+    #[test]
+    fn svg_for_three_points() {
+        let signals = vec![signal(2, 3, 1, 0), signal(5, 3, 0, 1), signal(4, 6, 0, 0)];
+        let edges = find_edges(&signals, 1).unwrap();
+        let svg = render_svg(&signals, 1, &edges);
+
+        assert!(svg.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains("viewBox=\"-1 -1 5 6\""));
+
+        let points: Vec<_> = svg
+            .lines()
+            .filter(|line| line.contains("fill=\"#ffff66\""))
+            .collect();
+        assert_eq!(
+            points,
+            [
+                "<rect x=\"0\" y=\"0\" width=\"1\" height=\"1\" fill=\"#ffff66\"/>",
+                "<rect x=\"2\" y=\"1\" width=\"1\" height=\"1\" fill=\"#ffff66\"/>",
+                "<rect x=\"1\" y=\"3\" width=\"1\" height=\"1\" fill=\"#ffff66\"/>",
+            ]
+        );
+    }
 
-    let (t, edges) = find_min_area(&signals);
+    #[test]
+    fn animation_centres_on_part_one() {
+        // A 2x2 square at t = 2.
+        let signals = vec![
+            signal(-4, -4, 2, 2),
+            signal(5, -4, -2, 2),
+            signal(-4, 5, 2, -2),
+            signal(5, 5, -2, -2),
+        ];
+        let minima = find_min_area_fast(&signals).unwrap();
+        assert_eq!(minima, [2]);
+
+        let positions = positions(&signals, 2);
+        let part_one = draw_positions(&positions, &bounds(&positions).unwrap(), false).unwrap();
+        assert_eq!(part_one, "##\n##\n");
+
+        let frames = frames(&signals, minima[0], 1).unwrap();
+        let times: Vec<_> = frames.iter().map(|&(t, _)| t).collect();
+        assert_eq!(times, [1, 2, 3]);
+        assert_eq!(frames[1].1.as_deref(), Some(part_one.as_str()));
+        assert!(frames.iter().all(|(_, frame)| frame.is_some()));
+    }
 
-    print!("Part 1:\n{}", draw(&signals, t, &edges));
-    println!("Part 2: {}", t);
+    #[test]
+    fn oversized_frames_are_skipped() {
+        let signals = vec![signal(0, 0, 0, 0), signal(1000, 1000, 1, 1)];
+        let frames = frames(&signals, 0, 1).unwrap();
 
-    Ok(())
+        assert_eq!(frames[0].1.as_deref(), None);
+        assert_eq!(frames[1].1.as_deref(), None);
+    }
 }
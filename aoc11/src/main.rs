@@ -1,54 +1,586 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufWriter, Error as IoError, ErrorKind};
+use std::num::ParseIntError;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
 const SIZE: usize = 300;
-const INPUT: usize = 9005;
+const SINGLE_THREADED: &str = "AOC11_SINGLE_THREADED";
 
-fn calculate(x: usize, y: usize) -> i8 {
-    let rack_id = x + 11;
-    (((rack_id * y + rack_id + INPUT) * rack_id) % 1000 / 100) as i8 - 5
+#[derive(Debug)]
+enum Error {
+    Io(IoError),
+    ParseInt(ParseIntError),
+    Usage(String),
+    Overflow { x: usize, y: usize },
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
 }
 
-fn populate(grid: &mut [[i8; SIZE]; SIZE]) {
-    for (y, row) in grid.iter_mut().enumerate() {
-        for (x, cell) in row.iter_mut().enumerate() {
-            *cell = calculate(x, y);
+impl From<ParseIntError> for Error {
+    fn from(error: ParseIntError) -> Self {
+        Error::ParseInt(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::ParseInt(e) => fmt::Display::fmt(e, f),
+            Error::Usage(e) => write!(f, "{}", e),
+            Error::Overflow { x, y } => write!(f, "power level overflows at ({}, {})", x, y),
         }
     }
 }
 
-fn sum(grid: &[[i8; SIZE]; SIZE], x: usize, y: usize, size: usize) -> i32 {
-    grid.iter()
-        .skip(y)
-        .take(size)
-        .flat_map(|y| y.iter().skip(x).take(size).cloned().map(i32::from))
-        .sum()
+struct Grid {
+    serial: u64,
+    size: usize,
+    cells: Vec<i8>,
 }
 
-fn part_one(grid: &[[i8; SIZE]; SIZE]) -> (usize, usize) {
-    let size = 2;
+impl Grid {
+    fn new(serial: u64, size: usize) -> Result<Self, Error> {
+        let mut grid = Grid {
+            serial,
+            size,
+            cells: vec![0; size * size],
+        };
+
+        grid.populate()?;
+        Ok(grid)
+    }
+
+    fn calculate(&self, x: usize, y: usize) -> Result<i8, Error> {
+        let rack_id = x as u64 + 11;
+        let power = rack_id
+            .checked_mul(y as u64)
+            .and_then(|power| power.checked_add(rack_id))
+            .and_then(|power| power.checked_add(self.serial))
+            .and_then(|power| power.checked_mul(rack_id))
+            .ok_or(Error::Overflow { x, y })?;
 
-    let (x, y) = (0..SIZE - size)
-        .flat_map(|y| (0..SIZE - size).map(move |x| (x, y)))
-        .max_by_key(|&(x, y)| sum(grid, x, y, size + 1))
-        .unwrap();
+        Ok((power % 1000 / 100) as i8 - 5)
+    }
+
+    fn populate(&mut self) -> Result<(), Error> {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                self.cells[y * self.size + x] = self.calculate(x, y)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn row(&self, y: usize) -> &[i8] {
+        &self.cells[y * self.size..(y + 1) * self.size]
+    }
 
-    (x + 1, y + 1)
+    fn rows(&self) -> impl Iterator<Item = &[i8]> {
+        (0..self.size).map(move |y| self.row(y))
+    }
+}
+
+fn summed_area(grid: &Grid) -> Vec<Vec<i32>> {
+    let mut table = vec![vec![0; grid.size + 1]; grid.size + 1];
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            table[y + 1][x + 1] = i32::from(cell) + table[y][x + 1] + table[y + 1][x] - table[y][x];
+        }
+    }
+
+    table
+}
+
+fn sum(table: &[Vec<i32>], x: usize, y: usize, size: usize) -> i32 {
+    table[y + size][x + size] - table[y][x + size] - table[y + size][x] + table[y][x]
 }
 
-fn part_two(grid: &[[i8; SIZE]; SIZE]) -> (usize, usize, usize) {
-    let (x, y, size) = (0..SIZE)
-        .flat_map(|size| {
-            (0..SIZE - size).flat_map(move |y| (0..SIZE - size).map(move |x| (x, y, size)))
+#[derive(Debug, PartialEq, Eq)]
+struct Best {
+    x: usize,
+    y: usize,
+    size: usize,
+    power: i32,
+}
+
+impl Best {
+    fn rank(&self) -> (i32, Reverse<usize>, Reverse<usize>, Reverse<usize>) {
+        (
+            self.power,
+            Reverse(self.size),
+            Reverse(self.y),
+            Reverse(self.x),
+        )
+    }
+
+    fn overlaps(&self, other: &Best) -> bool {
+        self.x < other.x + other.size
+            && other.x < self.x + self.size
+            && self.y < other.y + other.size
+            && other.y < self.y + self.size
+    }
+}
+
+fn best_for_size(table: &[Vec<i32>], size: usize, taken: &[Best]) -> Option<Best> {
+    let limit = table.len() - 1 - size;
+
+    (0..=limit)
+        .flat_map(|y| (0..=limit).map(move |x| (x, y)))
+        .map(|(x, y)| Best {
+            x: x + 1,
+            y: y + 1,
+            size,
+            power: sum(table, x, y, size),
         })
-        .max_by_key(|&(x, y, size)| sum(grid, x, y, size + 1))
-        .unwrap();
+        .filter(|best| !taken.iter().any(|other| best.overlaps(other)))
+        .max_by_key(Best::rank)
+}
+
+fn search_serial(table: &[Vec<i32>], sizes: RangeInclusive<usize>, taken: &[Best]) -> Option<Best> {
+    sizes
+        .filter_map(|size| best_for_size(table, size, taken))
+        .max_by_key(Best::rank)
+}
+
+fn search_parallel(
+    table: &[Vec<i32>],
+    sizes: RangeInclusive<usize>,
+    taken: &[Best],
+) -> Option<Best> {
+    sizes
+        .into_par_iter()
+        .filter_map(|size| best_for_size(table, size, taken))
+        .max_by_key(Best::rank)
+}
+
+fn search(table: &[Vec<i32>], sizes: RangeInclusive<usize>, taken: &[Best]) -> Option<Best> {
+    if env::var_os(SINGLE_THREADED).is_some() {
+        search_serial(table, sizes, taken)
+    } else {
+        search_parallel(table, sizes, taken)
+    }
+}
+
+fn best_square(grid: &Grid, sizes: RangeInclusive<usize>) -> Best {
+    let table = summed_area(grid);
+    let sizes = *sizes.start()..=*sizes.end().min(&grid.size);
 
-    (x + 1, y + 1, size + 1)
+    search(&table, sizes, &[]).unwrap()
 }
 
-fn main() {
-    let mut grid = [[0i8; SIZE]; SIZE];
+fn top_k(grid: &Grid, sizes: RangeInclusive<usize>, k: usize) -> Vec<Best> {
+    let table = summed_area(grid);
+    let sizes = *sizes.start()..=*sizes.end().min(&grid.size);
+    let mut taken = Vec::with_capacity(k);
+
+    while taken.len() < k {
+        match search(&table, sizes.clone(), &taken) {
+            Some(best) => taken.push(best),
+            None => break,
+        }
+    }
+
+    taken
+}
+
+fn render_ppm(grid: &Grid, best: &Best, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let (left, top) = (best.x - 1, best.y - 1);
+    let (right, bottom) = (left + best.size - 1, top + best.size - 1);
+
+    write!(out, "P6\n{} {}\n255\n", grid.size, grid.size)?;
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            let inside = (left..=right).contains(&x) && (top..=bottom).contains(&y);
+            let border = x == left || x == right || y == top || y == bottom;
+
+            let pixel = if inside && border {
+                [255, 255, 255]
+            } else {
+                let heat = (i32::from(cell) + 5) * 255 / 9;
+                [heat as u8, 0, (255 - heat) as u8]
+            };
+
+            out.write_all(&pixel)?;
+        }
+    }
+
+    out.flush()
+}
+
+fn parse_flag<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, Error> {
+    let value = value.ok_or_else(|| Error::Usage(format!("{} needs a value", flag)))?;
+
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value for {}: {}", flag, value)))
+}
+
+fn read_serial(path: &Path) -> Result<u64, Error> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(s.trim().parse()?),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Err(Error::Usage(format!(
+            "{} not found, pass --serial N",
+            path.display()
+        ))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let path = Path::new("inputs/input-11-01.txt");
+    let mut args = env::args().skip(1);
+    let (mut serial, mut size, mut render, mut top) = (None, SIZE, None, None);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--serial" => serial = Some(parse_flag(&arg, args.next())?),
+            "--size" => size = parse_flag(&arg, args.next())?,
+            "--top" => top = Some(parse_flag(&arg, args.next())?),
+            "--render" => {
+                render = Some(
+                    args.next()
+                        .ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?,
+                )
+            }
+            _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
+        }
+    }
+
+    if size < 3 {
+        return Err(Error::Usage(format!(
+            "grid size must be at least 3, got {}",
+            size
+        )));
+    }
 
-    populate(&mut grid);
+    let serial = match serial {
+        Some(serial) => serial,
+        None => read_serial(path)?,
+    };
+    let grid = Grid::new(serial, size)?;
 
-    println!("Part 1: {:?}", part_one(&grid));
-    println!("Part 2: {:?}", part_two(&grid));
+    let best = best_square(&grid, 3..=3);
+    println!("Part 1: {},{} (power {})", best.x, best.y, best.power);
+
+    let best = best_square(&grid, 1..=grid.size);
+    println!(
+        "Part 2: {},{},{} (power {})",
+        best.x, best.y, best.size, best.power
+    );
+
+    if let Some(k) = top {
+        for (rank, best) in top_k(&grid, 1..=grid.size, k).iter().enumerate() {
+            println!(
+                "Top {}: {},{},{} (power {})",
+                rank + 1,
+                best.x,
+                best.y,
+                best.size,
+                best.power
+            );
+        }
+    }
+
+    if let Some(render) = render {
+        render_ppm(&grid, &best, Path::new(&render))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_cells() {
+        for &(serial, x, y, power) in &[
+            (8, 3, 5, 4),
+            (57, 122, 79, -5),
+            (39, 217, 196, 0),
+            (71, 101, 153, 4),
+        ] {
+            let grid = Grid::new(serial, SIZE).unwrap();
+
+            assert_eq!(grid.row(y - 1)[x - 1], power, "serial {}", serial);
+        }
+    }
+
+    #[test]
+    fn serial_from_file() {
+        let path = env::temp_dir().join("aoc11-serial.txt");
+
+        fs::write(&path, "7403\n").unwrap();
+        assert_eq!(read_serial(&path).unwrap(), 7403);
+
+        fs::write(&path, "serial\n").unwrap();
+        assert!(matches!(read_serial(&path), Err(Error::ParseInt(_))));
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(read_serial(&path), Err(Error::Usage(_))));
+    }
+
+    #[test]
+    fn serial_flag() {
+        assert_eq!(
+            parse_flag::<u64>("--serial", Some("42".to_string())).unwrap(),
+            42
+        );
+        assert!(matches!(
+            parse_flag::<u64>("--serial", None),
+            Err(Error::Usage(_))
+        ));
+        assert!(matches!(
+            parse_flag::<u64>("--serial", Some("-1".to_string())),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn best_square_power() {
+        let grid = Grid::new(18, SIZE).unwrap();
+
+        assert_eq!(best_square(&grid, 3..=3).power, 29);
+        assert_eq!(best_square(&grid, 16..=16).power, 113);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let table = summed_area(&Grid::new(42, SIZE).unwrap());
+        let parallel = search_parallel(&table, 1..=SIZE, &[]).unwrap();
+
+        assert_eq!(parallel, search_serial(&table, 1..=SIZE, &[]).unwrap());
+        assert_eq!(
+            search_parallel(&table, 3..=3, &[]),
+            search_serial(&table, 3..=3, &[])
+        );
+    }
+
+    #[test]
+    fn ties_prefer_small_squares() {
+        let grid = Grid {
+            serial: 0,
+            size: 4,
+            cells: vec![0; 16],
+        };
+        let table = summed_area(&grid);
+        let first = Best {
+            x: 1,
+            y: 1,
+            size: 1,
+            power: 0,
+        };
+
+        assert_eq!(search_parallel(&table, 1..=4, &[]).unwrap(), first);
+        assert_eq!(search_serial(&table, 1..=4, &[]).unwrap(), first);
+    }
+
+    fn best(x: usize, y: usize, size: usize, power: i32) -> Best {
+        Best { x, y, size, power }
+    }
+
+    #[test]
+    fn published_squares() {
+        let (grid_18, grid_42) = (Grid::new(18, SIZE).unwrap(), Grid::new(42, SIZE).unwrap());
+
+        assert_eq!(best_square(&grid_18, 3..=3), best(33, 45, 3, 29));
+        assert_eq!(best_square(&grid_42, 3..=3), best(21, 61, 3, 30));
+        assert_eq!(best_square(&grid_18, 1..=SIZE), best(90, 269, 16, 113));
+        assert_eq!(best_square(&grid_42, 1..=SIZE), best(232, 251, 12, 119));
+    }
+
+    #[test]
+    fn small_grid() {
+        // All -1 except a 3x3 block of 4s with its corner at (5, 6).
+        let mut grid = Grid {
+            serial: 0,
+            size: 10,
+            cells: vec![-1; 100],
+        };
+        for y in 5..8 {
+            for x in 4..7 {
+                grid.cells[y * 10 + x] = 4;
+            }
+        }
+
+        assert_eq!(best_square(&grid, 1..=10), best(5, 6, 3, 36));
+        assert_eq!(best_square(&grid, 4..=10), best(4, 5, 4, 29));
+        assert_eq!(best_square(&grid, 1..=SIZE), best(5, 6, 3, 36));
+    }
+
+    #[test]
+    fn small_grid_brute_force() {
+        let grid = Grid::new(18, 10).unwrap();
+        let mut expected = best(0, 0, 0, i32::MIN);
+
+        for size in 1..=10 {
+            for y in 0..=10 - size {
+                for x in 0..=10 - size {
+                    let power = (y..y + size)
+                        .map(|y| {
+                            grid.row(y)[x..x + size]
+                                .iter()
+                                .map(|&c| i32::from(c))
+                                .sum::<i32>()
+                        })
+                        .sum();
+
+                    if power > expected.power {
+                        expected = best(x + 1, y + 1, size, power);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(best_square(&grid, 1..=10), expected);
+    }
+
+    #[test]
+    fn ppm_heatmap() {
+        // All -5 except a 3x3 block of 4s at (3, 3) and a single 0 in the
+        // bottom-right corner.
+        let mut grid = Grid {
+            serial: 0,
+            size: 8,
+            cells: vec![-5; 64],
+        };
+        for y in 2..5 {
+            for x in 2..5 {
+                grid.cells[y * 8 + x] = 4;
+            }
+        }
+        grid.cells[63] = 0;
+
+        let best = best_square(&grid, 1..=8);
+        assert_eq!(best, self::best(3, 3, 3, 36));
+
+        let path = env::temp_dir().join("aoc11-heatmap.ppm");
+        render_ppm(&grid, &best, &path).unwrap();
+        let ppm = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let header = b"P6\n8 8\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 8 * 8 * 3);
+
+        let pixel = |x: usize, y: usize| {
+            let i = header.len() + (y * 8 + x) * 3;
+            [ppm[i], ppm[i + 1], ppm[i + 2]]
+        };
+        assert_eq!(pixel(0, 0), [0, 0, 255]);
+        assert_eq!(pixel(7, 7), [141, 0, 114]);
+        assert_eq!(pixel(3, 3), [255, 0, 0]);
+        assert_eq!(pixel(2, 2), [255, 255, 255]);
+        assert_eq!(pixel(4, 3), [255, 255, 255]);
+        assert_eq!(pixel(5, 3), [0, 0, 255]);
+    }
+
+    #[test]
+    fn serials_near_u32_max() {
+        let max = u64::from(u32::MAX);
+
+        for &serial in &[max - 1, max, max + 7, 1 << 40] {
+            let grid = Grid {
+                serial,
+                size: 0,
+                cells: vec![],
+            };
+
+            for &(x, y) in &[(0, 0), (299, 299), (122, 79)] {
+                // The same formula in u128, which can't overflow here.
+                let rack_id = x as u128 + 11;
+                let power = (rack_id * y as u128 + rack_id + u128::from(serial)) * rack_id;
+
+                assert_eq!(
+                    grid.calculate(x, y).unwrap(),
+                    (power % 1000 / 100) as i8 - 5,
+                    "serial {} at ({}, {})",
+                    serial,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn power_overflow() {
+        let grid = Grid {
+            serial: u64::MAX,
+            size: 0,
+            cells: vec![],
+        };
+
+        assert!(matches!(
+            grid.calculate(5, 7),
+            Err(Error::Overflow { x: 5, y: 7 })
+        ));
+        assert!(matches!(
+            Grid::new(u64::MAX, 3),
+            Err(Error::Overflow { x: 0, y: 0 })
+        ));
+    }
+
+    #[test]
+    fn top_squares_on_serial_18() {
+        let grid = Grid::new(18, SIZE).unwrap();
+        let top = top_k(&grid, 1..=SIZE, 3);
+
+        assert_eq!(top.len(), 3);
+        assert_eq!(top[0], best_square(&grid, 1..=SIZE));
+        assert!(top.windows(2).all(|w| w[0].power >= w[1].power));
+        assert!(!top[1].overlaps(&top[0]));
+        assert!(!top[2].overlaps(&top[0]) && !top[2].overlaps(&top[1]));
+    }
+
+    #[test]
+    fn top_squares_skip_overlaps() {
+        // The 2x2 at (2, 2) is worth 20 and the one beside it at (3, 2) 18,
+        // but they overlap, so the runner-up is the disjoint 12 at (5, 5).
+        let grid = Grid {
+            serial: 0,
+            size: 6,
+            cells: vec![
+                0, 0, 0, 0, 0, 0, //
+                0, 5, 5, 4, 0, 0, //
+                0, 5, 5, 4, 0, 0, //
+                0, 0, 0, 0, 0, 0, //
+                0, 0, 0, 0, 3, 3, //
+                0, 0, 0, 0, 3, 3, //
+            ],
+        };
+
+        assert_eq!(
+            top_k(&grid, 2..=2, 2),
+            [best(2, 2, 2, 20), best(5, 5, 2, 12)]
+        );
+    }
+
+    #[test]
+    fn top_squares_run_out() {
+        let grid = Grid {
+            serial: 0,
+            size: 4,
+            cells: vec![1; 16],
+        };
+
+        assert_eq!(top_k(&grid, 3..=4, 5), [best(1, 1, 4, 16)]);
+        assert_eq!(top_k(&grid, 2..=2, 10).len(), 4);
+    }
 }
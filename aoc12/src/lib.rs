@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader, Error as IoError};
+use std::iter;
+use std::mem;
+use std::path::Path;
+
+pub const MAX_RADIUS: usize = 8;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Invalid(String),
+    Usage(String),
+    Overflow,
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(e) => write!(f, "invalid input: {}", e),
+            Error::Usage(e) => write!(f, "{}", e),
+            Error::Overflow => write!(f, "pot sum overflows i64"),
+        }
+    }
+}
+
+fn parse_pots(s: &str) -> Option<Vec<u8>> {
+    s.bytes()
+        .map(|b| match b {
+            b'.' => Some(0),
+            b'#' => Some(1),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub radius: usize,
+    pub table: Vec<u8>,
+}
+
+impl Rules {
+    pub fn new(radius: usize) -> Self {
+        Rules {
+            radius,
+            table: vec![0; 1 << (2 * radius + 1)],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        2 * self.radius + 1
+    }
+
+    pub fn mask(&self) -> usize {
+        self.table.len() - 1
+    }
+}
+
+fn parse_rule(s: &str) -> Option<(Vec<u8>, u8)> {
+    let mut split = s.split("=>");
+    let pattern = parse_pots(split.next()?.trim())?;
+    let result = parse_pots(split.next()?.trim())?;
+
+    if pattern.len() % 2 == 0
+        || pattern.len() > 2 * MAX_RADIUS + 1
+        || result.len() != 1
+        || split.next().is_some()
+    {
+        return None;
+    }
+
+    Some((pattern, result[0]))
+}
+
+pub fn parse_input(path: &Path) -> Result<(Vec<u8>, Rules), Error> {
+    parse(BufReader::new(File::open(path)?))
+}
+
+pub fn parse<R: BufRead>(reader: R) -> Result<(Vec<u8>, Rules), Error> {
+    let mut initial = None;
+    let mut rules: Option<Rules> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() || line.starts_with("#!") {
+            continue;
+        }
+
+        if let Some(s) = line.strip_prefix("initial state:") {
+            if initial.is_some() {
+                return Err(Error::Invalid(format!(
+                    "duplicate initial state: {:?}",
+                    line
+                )));
+            }
+
+            initial = Some(
+                parse_pots(s.trim())
+                    .ok_or_else(|| Error::Invalid(format!("bad initial state: {:?}", line)))?,
+            );
+            continue;
+        }
+
+        let (pattern, result) =
+            parse_rule(line).ok_or_else(|| Error::Invalid(format!("bad rule: {:?}", line)))?;
+        let rules = rules.get_or_insert_with(|| Rules::new(pattern.len() / 2));
+
+        if pattern.len() != rules.width() {
+            return Err(Error::Invalid(format!(
+                "rule width differs from {}: {:?}",
+                rules.width(),
+                line
+            )));
+        }
+
+        let index = pattern.iter().fold(0, |index, &x| index << 1 | x as usize);
+        rules.table[index] = result;
+    }
+
+    let initial =
+        initial.ok_or_else(|| Error::Invalid("missing initial state section".to_owned()))?;
+    let rules = rules.ok_or_else(|| Error::Invalid("missing rules section".to_owned()))?;
+
+    if rules.table[0] == 1 {
+        return Err(Error::Invalid(format!(
+            "rule '{}' => '#' produces unbounded growth",
+            ".".repeat(rules.width())
+        )));
+    }
+
+    Ok((initial, rules))
+}
+
+pub fn advance(prev: &[u8], rules: &Rules) -> (Vec<u8>, i64) {
+    let padding = 2 * rules.radius;
+    let mut next = Vec::with_capacity(prev.len() + padding);
+    let mut index = 0;
+    let mut first = false;
+    let mut shift = -(rules.radius as i64);
+
+    for &x in prev.iter().chain(iter::repeat_n(&0, padding)) {
+        index = index << 1 | x as usize;
+
+        match (first, rules.table[index & rules.mask()]) {
+            (false, 1) => {
+                first = true;
+                next.push(1);
+            }
+            (true, x) => next.push(x),
+            _ => shift += 1,
+        }
+    }
+
+    while next.last() == Some(&0) {
+        next.pop();
+    }
+
+    (next, shift)
+}
+
+fn pot_sum(pots: &[u8], shift: i64) -> Result<i64, Error> {
+    pots.iter()
+        .enumerate()
+        .filter(|&(_, &x)| x == 1)
+        .try_fold(0i64, |sum, (i, _)| {
+            (i as i64)
+                .checked_add(shift)
+                .and_then(|pot| sum.checked_add(pot))
+        })
+        .ok_or(Error::Overflow)
+}
+
+pub struct Generations<'a> {
+    rules: &'a Rules,
+    pots: Vec<u8>,
+    offset: i64,
+}
+
+impl<'a> Generations<'a> {
+    pub fn new(initial: &[u8], rules: &'a Rules) -> Self {
+        Generations {
+            rules,
+            pots: Vec::from(initial),
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Generations<'a> {
+    type Item = (i64, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pots, shift) = advance(&self.pots, self.rules);
+        let offset = self.offset;
+        self.offset += shift;
+
+        Some((offset, mem::replace(&mut self.pots, pots)))
+    }
+}
+
+pub fn evolve(initial: &[u8], rules: &Rules, generations: usize) -> Result<i64, Error> {
+    let mut states = Generations::new(initial, rules);
+    let mut seen = HashMap::new();
+    let mut history: Vec<(i64, Vec<u8>)> = Vec::new();
+
+    for (i, (offset, pots)) in states.by_ref().take(generations).enumerate() {
+        if let Some(j) = seen.insert(pots.clone(), i) {
+            let (period, remaining) = (i - j, generations - i);
+            let delta = offset - history[j].0;
+            let (o, pots) = &history[j + remaining % period];
+
+            let shift = i64::try_from(remaining / period)
+                .ok()
+                .and_then(|periods| periods.checked_mul(delta))
+                .and_then(|drift| drift.checked_add(offset))
+                .and_then(|shift| shift.checked_add(o - history[j].0))
+                .ok_or(Error::Overflow)?;
+
+            return pot_sum(pots, shift);
+        }
+
+        history.push((offset, pots));
+    }
+
+    let (offset, pots) = states.next().unwrap();
+    pot_sum(&pots, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+initial state: #..#.#..##......###...###
+
+...## => #
+..#.. => #
+.#... => #
+.#.#. => #
+.#.## => #
+.##.. => #
+.#### => #
+#.#.# => #
+#.### => #
+##.#. => #
+##.## => #
+###.. => #
+###.# => #
+####. => #
+";
+
+    // The puzzle's chart of pots -3 through 35.
+    const CHART: [&str; 21] = [
+        "...#..#.#..##......###...###...........",
+        "...#...#....#.....#..#..#..#...........",
+        "...##..##...##....#..#..#..##..........",
+        "..#.#...#..#.#....#..#..#...#..........",
+        "...#.#..#...#.#...#..#..##..##.........",
+        "....#...##...#.#..#..#...#...#.........",
+        "....##.#.#....#...#..##..##..##........",
+        "...#..###.#...##..#...#...#...#........",
+        "...#....##.#.#.#..##..##..##..##.......",
+        "...##..#..#####....#...#...#...#.......",
+        "..#.#..#...#.##....##..##..##..##......",
+        "...#...##...#.#...#.#...#...#...#......",
+        "...##.#.#....#.#...#.#..##..##..##.....",
+        "..#..###.#....#.#...#....#...#...#.....",
+        "..#....##.#....#.#..##...##..##..##....",
+        "..##..#..#.#....#....#..#.#...#...#....",
+        ".#.#..#...#.#...##...#...#.#..##..##...",
+        "..#...##...#.#.#.#...##...#....#...#...",
+        "..##.#.#....#####.#.#.#...##...##..##..",
+        ".#..###.#..#.#.#######.#.#.#..#.#...#..",
+        ".#....##....#####...#######....#.#..##.",
+    ];
+
+    fn example() -> (Vec<u8>, Rules) {
+        parse(EXAMPLE.as_bytes()).unwrap()
+    }
+
+    fn invalid(input: &str) -> String {
+        match parse(input.as_bytes()) {
+            Err(e @ Error::Invalid(_)) => e.to_string(),
+            other => panic!("expected Error::Invalid, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn example_rules() {
+        let (initial, rules) = example();
+
+        assert_eq!(initial.len(), 25);
+        assert_eq!(rules.radius, 2);
+        assert_eq!(rules.table.iter().filter(|&&x| x == 1).count(), 14);
+        assert_eq!(rules.table[0b00011], 1);
+        assert_eq!(rules.table[0b11111], 0);
+    }
+
+    #[test]
+    fn crlf() {
+        assert_eq!(
+            parse(EXAMPLE.replace('\n', "\r\n").as_bytes()).unwrap(),
+            example()
+        );
+    }
+
+    #[test]
+    fn shuffled_rules() {
+        let mut lines: Vec<_> = EXAMPLE.lines().skip(2).collect();
+        lines.reverse();
+        let rules: Vec<_> = lines
+            .iter()
+            .map(|line| line.replace(" => ", "=>"))
+            .collect();
+        let input = format!(
+            "initial state: #..#.#..##......###...###\n\n\n{}\n\n",
+            rules.join("\n")
+        );
+
+        assert_eq!(parse(input.as_bytes()).unwrap(), example());
+    }
+
+    #[test]
+    fn bad_lines_are_quoted() {
+        assert!(invalid("initial state: #.\n\n..#. => #\n").contains("..#. => #"));
+        assert!(invalid("init: #.\n").contains("init: #."));
+        assert!(invalid("initial state: #x\n").contains("#x"));
+        assert!(invalid("initial state: #\n\n..#.. => ##\n").contains("..#.. => ##"));
+    }
+
+    #[test]
+    fn unbounded_growth() {
+        let error = invalid(&format!("{}..... => #\n", EXAMPLE));
+
+        assert!(error.contains("'.....' => '#'"), "{}", error);
+        assert!(error.contains("unbounded growth"), "{}", error);
+    }
+
+    #[test]
+    fn empty_rule_to_empty_pot() {
+        assert_eq!(
+            parse(format!("{}..... => .\n", EXAMPLE).as_bytes()).unwrap(),
+            example()
+        );
+    }
+
+    fn brute_force(initial: &[u8], rules: &Rules, generations: usize) -> i64 {
+        let (mut pots, mut offset) = (initial.to_vec(), 0);
+
+        for _ in 0..generations {
+            let (next, shift) = advance(&pots, rules);
+            pots = next;
+            offset += shift;
+        }
+
+        pot_sum(&pots, offset).unwrap()
+    }
+
+    #[test]
+    fn drifting_blinker() {
+        // '#' becomes '##' in place, which becomes '#' two pots to the right.
+        let (initial, rules) =
+            parse("initial state: #\n\n..#.. => #\n.#... => #\n##... => #\n".as_bytes()).unwrap();
+        let states: Vec<_> = Generations::new(&initial, &rules).take(5).collect();
+
+        assert_eq!(states[1], (0, vec![1, 1]));
+        assert_eq!(states[2], (2, vec![1]));
+        assert_eq!(states[4], (4, vec![1]));
+
+        for &generations in &[0, 1, 2, 3, 1000, 2999, 3000] {
+            assert_eq!(
+                evolve(&initial, &rules, generations).unwrap(),
+                brute_force(&initial, &rules, generations),
+                "{} generations",
+                generations
+            );
+        }
+        assert_eq!(
+            evolve(&initial, &rules, 50_000_000_001).unwrap(),
+            2 * 50_000_000_001 - 1
+        );
+    }
+
+    #[test]
+    fn random_rules_match_brute_force() {
+        let mut state = 12345u64;
+        let initial = [1, 0, 1, 1, 0, 0, 1];
+
+        for _ in 0..100 {
+            let mut rules = Rules::new(2);
+
+            for x in rules.table.iter_mut().skip(1) {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                *x = (state >> 40) as u8 & 1;
+            }
+
+            for &generations in &[0, 1, 7, 100, 500] {
+                assert_eq!(
+                    evolve(&initial, &rules, generations).unwrap(),
+                    brute_force(&initial, &rules, generations),
+                    "{:?} after {} generations",
+                    rules,
+                    generations
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn example_generations() {
+        let (initial, rules) = example();
+        let states: Vec<_> = Generations::new(&initial, &rules).take(21).collect();
+
+        for (i, ((offset, pots), line)) in states.iter().zip(&CHART).enumerate() {
+            let row: String = (-3..36)
+                .map(|pot| {
+                    let plant = usize::try_from(pot - offset)
+                        .ok()
+                        .and_then(|index| pots.get(index))
+                        == Some(&1);
+                    if plant {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            assert_eq!(row, *line, "generation {}", i);
+        }
+
+        let (offset, pots) = &states[20];
+        assert_eq!(pot_sum(pots, *offset).unwrap(), 325);
+    }
+
+    #[test]
+    fn rule_90() {
+        let (initial, rules) =
+            parse("initial state: #\n\n#.. => #\n..# => #\n##. => #\n.## => #\n".as_bytes())
+                .unwrap();
+        let states: Vec<_> = Generations::new(&initial, &rules).take(5).collect();
+
+        assert_eq!(rules.radius, 1);
+        assert_eq!(states[1], (-1, vec![1, 0, 1]));
+        assert_eq!(states[2], (-2, vec![1, 0, 0, 0, 1]));
+        assert_eq!(states[3], (-3, vec![1, 0, 1, 0, 1, 0, 1]));
+        assert_eq!(states[4], (-4, vec![1, 0, 0, 0, 0, 0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn example_still_radius_two() {
+        let (initial, rules) = example();
+
+        assert_eq!(evolve(&initial, &rules, 20).unwrap(), 325);
+    }
+
+    #[test]
+    fn radius_from_rule_width() {
+        let (_, rules) = parse("initial state: #\n\n...#... => #\n".as_bytes()).unwrap();
+        assert_eq!((rules.radius, rules.table.len()), (3, 128));
+
+        assert!(invalid("initial state: #\n\n#.. => #\n..#.. => #\n").contains("width"));
+        let wide = format!(
+            "initial state: #\n\n{} => #\n",
+            ".".repeat(2 * MAX_RADIUS + 3)
+        );
+        assert!(invalid(&wide).contains("bad rule"));
+    }
+
+    #[test]
+    fn long_drift() {
+        let (initial, rules) = parse("initial state: #\n\n..# => #\n".as_bytes()).unwrap();
+        assert_eq!(
+            evolve(&initial, &rules, 50_000_000_000).unwrap(),
+            -50_000_000_000
+        );
+
+        // The widest rule moves a lone plant MAX_RADIUS pots per generation.
+        let input = format!("initial state: #\n\n{}# => #\n", ".".repeat(2 * MAX_RADIUS));
+        let (initial, rules) = parse(input.as_bytes()).unwrap();
+        assert_eq!(evolve(&initial, &rules, 1_000_000).unwrap(), -8_000_000);
+        assert_eq!(
+            evolve(&initial, &rules, 50_000_000_000).unwrap(),
+            -400_000_000_000
+        );
+    }
+
+    #[test]
+    fn drift_overflow() {
+        let input = format!("initial state: #\n\n{}# => #\n", ".".repeat(2 * MAX_RADIUS));
+        let (initial, rules) = parse(input.as_bytes()).unwrap();
+
+        assert!(matches!(
+            evolve(&initial, &rules, usize::MAX),
+            Err(Error::Overflow)
+        ));
+        assert!(matches!(
+            evolve(&initial, &rules, usize::MAX / 4),
+            Err(Error::Overflow)
+        ));
+        assert!(matches!(pot_sum(&[1, 1], i64::MAX), Err(Error::Overflow)));
+        assert_eq!(pot_sum(&[0, 0, 1], i64::MAX - 2).unwrap(), i64::MAX);
+    }
+
+    #[test]
+    fn zero_generations() {
+        let (initial, rules) = example();
+
+        assert_eq!(evolve(&initial, &rules, 0).unwrap(), 145);
+    }
+
+    #[test]
+    fn advance_empty() {
+        let (_, rules) = example();
+
+        assert_eq!(advance(&[], &rules), (vec![], 2));
+    }
+
+    #[test]
+    fn moving_left() {
+        // A lone plant moves one pot left per generation.
+        let (initial, rules) =
+            parse("initial state: #\n\n..#.. => .\n...#. => #\n".as_bytes()).unwrap();
+
+        assert_eq!(advance(&initial, &rules), (vec![1], -1));
+        assert_eq!(evolve(&initial, &rules, 3).unwrap(), -3);
+        assert_eq!(evolve(&initial, &rules, 1000).unwrap(), -1000);
+
+        let (initial, rules) = parse("initial state: #....#\n\n...#. => #\n".as_bytes()).unwrap();
+        assert_eq!(evolve(&initial, &rules, 3).unwrap(), -3 + 2);
+    }
+
+    #[test]
+    fn rules_before_initial_state() {
+        let mut lines: Vec<_> = EXAMPLE.lines().collect();
+        let first = lines.remove(0);
+        lines.push(first);
+
+        assert_eq!(parse(lines.join("\n").as_bytes()).unwrap(), example());
+    }
+
+    #[test]
+    fn comments() {
+        let input = format!(
+            "#! normalized\n{}#! end\n",
+            EXAMPLE.replace("\n\n", "\n#! rules\n")
+        );
+
+        assert_eq!(parse(input.as_bytes()).unwrap(), example());
+    }
+
+    #[test]
+    fn missing_sections() {
+        assert!(invalid("#! x\n..#.. => #\n").contains("missing initial state"));
+        assert!(invalid("initial state: #\n").contains("missing rules"));
+        assert!(invalid("initial state: #\ninitial state: #\n").contains("duplicate"));
+    }
+}
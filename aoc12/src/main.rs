@@ -1,145 +1,193 @@
-use std::fmt;
-use std::fs::File;
-use std::io::{prelude::*, BufReader, Error as IoError};
+use aoc12::{evolve, parse, parse_input, Error, Generations, Rules};
+use std::convert::TryFrom;
+use std::env;
+use std::io;
 use std::path::Path;
 
-const TABLE: usize = 1 << 5;
-const MASK: usize = 0b0001_1111;
 const GEN_1: usize = 20;
 const GEN_2: usize = 50_000_000_000;
 
-#[derive(Debug)]
-enum Error {
-    Io(IoError),
-    Invalid,
-}
+fn show(initial: &[u8], rules: &Rules, n: usize) {
+    let states: Vec<_> = Generations::new(initial, rules).take(n).collect();
+    let min = states.iter().map(|&(offset, _)| offset).min().unwrap_or(0);
+    let width = n.saturating_sub(1).to_string().len();
 
-impl From<IoError> for Error {
-    fn from(error: IoError) -> Self {
-        Error::Io(error)
-    }
-}
+    for (i, (offset, pots)) in states.iter().enumerate() {
+        let row: String = pots
+            .iter()
+            .map(|&x| if x == 1 { '#' } else { '.' })
+            .collect();
+        let pad = ".".repeat((offset - min) as usize);
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Error::Io(e) => fmt::Display::fmt(e, f),
-            Error::Invalid => write!(f, "invalid input"),
-        }
+        println!("{:>width$}: {}{}", i, pad, row, width = width);
     }
 }
 
-fn parse_input(path: &Path) -> Result<(Vec<u8>, [u8; TABLE]), Error> {
-    let mut buf = BufReader::new(File::open(path)?).lines();
-
-    let s = match buf.next() {
-        Some(Ok(s)) => {
-            if s.len() > 17 {
-                s
+fn render_chart(initial: &[u8], rules: &Rules, n: usize) -> String {
+    let states: Vec<_> = Generations::new(initial, rules).take(n + 1).collect();
+    let plants = states.iter().filter(|(_, pots)| !pots.is_empty());
+    let left = plants.clone().map(|&(offset, _)| offset).min().unwrap_or(0) - 1;
+    let right = plants
+        .map(|(offset, pots)| offset + pots.len() as i64 - 1)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let width = n.to_string().len();
+    let labels: Vec<_> = (left..=right)
+        .map(|pot| {
+            if pot % 10 == 0 {
+                pot.to_string()
             } else {
-                return Err(Error::Invalid);
+                String::new()
             }
+        })
+        .collect();
+    let height = labels.iter().map(String::len).max().unwrap_or(0);
+    let mut chart = String::new();
+
+    for line in 0..height {
+        chart.push_str(&" ".repeat(width + 2));
+
+        for label in &labels {
+            let skip = height - label.len();
+            chart.push(if line < skip {
+                ' '
+            } else {
+                label.as_bytes()[line - skip] as char
+            });
         }
-        Some(Err(e)) => return Err(e.into()),
-        None => return Err(Error::Invalid),
-    };
-
-    let s = &s[15..];
-
-    let mut initial = Vec::with_capacity(s.len());
 
-    for b in s.bytes() {
-        match b {
-            b'.' => initial.push(0),
-            b'#' => initial.push(1),
-            _ => return Err(Error::Invalid),
-        }
+        chart.push('\n');
     }
 
-    let mut table = [0u8; TABLE];
-
-    for s in buf.skip(1).take(TABLE) {
-        let s = s?;
+    for (i, (offset, pots)) in states.iter().enumerate() {
+        chart.push_str(&format!("{:>width$}: ", i, width = width));
 
-        match s.bytes().nth(9) {
-            Some(b'.') => continue,
-            Some(b'#') => (),
-            _ => return Err(Error::Invalid),
+        for pot in left..=right {
+            let plant = usize::try_from(pot - offset)
+                .ok()
+                .and_then(|index| pots.get(index))
+                == Some(&1);
+            chart.push(if plant { '#' } else { '.' });
         }
 
-        let mut index = 0;
+        chart.push('\n');
+    }
 
-        for b in s.bytes().take(5) {
-            index <<= 1;
+    chart
+}
 
-            match b {
-                b'.' => (),
-                b'#' => index |= 1,
-                _ => return Err(Error::Invalid),
-            }
-        }
-        table[index] = 1;
-    }
+fn parse_flag(flag: &str, value: Option<String>) -> Result<usize, Error> {
+    let value = value.ok_or_else(|| Error::Usage(format!("{} needs a value", flag)))?;
 
-    Ok((initial, table))
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value for {}: {}", flag, value)))
 }
 
-fn advance(prev: &[u8], table: &[u8; TABLE]) -> (Vec<u8>, i64) {
-    let mut next = Vec::with_capacity(prev.len() + 4);
-    let mut index = 0;
-    let mut first = false;
-    let mut shift = -2;
+fn main() -> Result<(), Error> {
+    let path = Path::new("inputs/input-12-01.txt");
 
-    for &x in prev.iter().chain([0, 0, 0, 0].iter()) {
-        index = index << 1 | x as usize;
+    let mut args = env::args().skip(1);
+    let (mut generations, mut chart, mut file) = (None, None, None);
 
-        match (first, table[index & MASK]) {
-            (false, 1) => {
-                first = true;
-                next.push(1);
-            }
-            (true, x) => next.push(x),
-            _ => shift += 1,
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show" => generations = Some(parse_flag(&arg, args.next())?),
+            "--chart" => chart = Some(parse_flag(&arg, args.next())?),
+            _ if !arg.starts_with("--") && file.is_none() => file = Some(arg),
+            _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
         }
     }
 
-    while next.last() == Some(&0) {
-        next.pop();
-    }
-
-    (next, shift)
-}
+    let (initial, rules) = match file {
+        Some(ref file) if file == "-" => parse(io::stdin().lock())?,
+        Some(file) => parse_input(Path::new(&file))?,
+        None if path.exists() => parse_input(path)?,
+        None => parse(io::stdin().lock())?,
+    };
 
-fn evolve(initial: &[u8], table: &[u8; TABLE], generations: usize) -> i64 {
-    let mut shift = 0;
-    let mut vec = Vec::from(initial);
+    if let Some(n) = generations {
+        show(&initial, &rules, n);
+    }
 
-    for i in 0..generations {
-        let (v, s) = advance(&vec, table);
+    if let Some(n) = chart {
+        print!("{}", render_chart(&initial, &rules, n));
+    }
 
-        if vec == v {
-            shift += (generations - i) as i64 * s;
-            break;
-        }
+    println!("Part 1: {:?}", evolve(&initial, &rules, GEN_1)?);
+    println!("Part 2: {:?}", evolve(&initial, &rules, GEN_2)?);
 
-        shift += s;
+    Ok(())
+}
 
-        vec = v;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+initial state: #..#.#..##......###...###
+
+...## => #
+..#.. => #
+.#... => #
+.#.#. => #
+.#.## => #
+.##.. => #
+.#### => #
+#.#.# => #
+#.### => #
+##.#. => #
+##.## => #
+###.. => #
+###.# => #
+####. => #
+";
+
+    const CHART: &str = "                 1         2         3     
+       0         0         0         0     
+ 0: ...#..#.#..##......###...###...........
+ 1: ...#...#....#.....#..#..#..#...........
+ 2: ...##..##...##....#..#..#..##..........
+ 3: ..#.#...#..#.#....#..#..#...#..........
+ 4: ...#.#..#...#.#...#..#..##..##.........
+ 5: ....#...##...#.#..#..#...#...#.........
+ 6: ....##.#.#....#...#..##..##..##........
+ 7: ...#..###.#...##..#...#...#...#........
+ 8: ...#....##.#.#.#..##..##..##..##.......
+ 9: ...##..#..#####....#...#...#...#.......
+10: ..#.#..#...#.##....##..##..##..##......
+11: ...#...##...#.#...#.#...#...#...#......
+12: ...##.#.#....#.#...#.#..##..##..##.....
+13: ..#..###.#....#.#...#....#...#...#.....
+14: ..#....##.#....#.#..##...##..##..##....
+15: ..##..#..#.#....#....#..#.#...#...#....
+16: .#.#..#...#.#...##...#...#.#..##..##...
+17: ..#...##...#.#.#.#...##...#....#...#...
+18: ..##.#.#....#####.#.#.#...##...##..##..
+19: .#..###.#..#.#.#######.#.#.#..#.#...#..
+20: .#....##....#####...#######....#.#..##.
+";
+
+    fn trimmed(s: &str) -> Vec<&str> {
+        s.lines().map(str::trim_end).collect()
     }
 
-    vec.iter()
-        .enumerate()
-        .filter_map(|(i, &x)| if x == 1 { Some(i as i64 + shift) } else { None })
-        .sum()
-}
-
-fn main() -> Result<(), Error> {
-    let path = Path::new("inputs/input-12-01.txt");
+    #[test]
+    fn example_chart() {
+        let (initial, rules) = parse(EXAMPLE.as_bytes()).unwrap();
 
-    let (initial, table) = parse_input(path)?;
+        assert_eq!(trimmed(&render_chart(&initial, &rules, 20)), trimmed(CHART));
+    }
 
-    println!("Part 1: {:?}", evolve(&initial, &table, GEN_1));
-    println!("Part 2: {:?}", evolve(&initial, &table, GEN_2));
+    #[test]
+    fn chart_of_nothing() {
+        let (initial, rules) = parse("initial state: .\n\n..#.. => #\n".as_bytes()).unwrap();
 
-    Ok(())
+        assert_eq!(
+            trimmed(&render_chart(&initial, &rules, 1)),
+            ["    0", "0: ...", "1: ..."]
+        );
+    }
 }
@@ -0,0 +1,1009 @@
+use std::fmt;
+use std::io::Error as IoError;
+use std::iter;
+use std::str::FromStr;
+
+use self::Direction::*;
+use self::NextTurn::*;
+use self::Object::*;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Invalid(String),
+    Derailed {
+        position: Position,
+        direction: Direction,
+    },
+    OffMap(Position),
+    Usage(String),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(s) => write!(f, "invalid input: {}", s),
+            Error::Derailed {
+                position,
+                direction,
+            } => write!(f, "cart heading {:?} derailed at {}", direction, position),
+            Error::OffMap(position) => write!(f, "cart left the map at {}", position),
+            Error::Usage(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+enum Object {
+    Empty,
+    Horizontal,
+    Vertical,
+    NWSEEdge,
+    NESWEdge,
+    Intersection,
+}
+
+impl Object {
+    fn glyph(&self) -> char {
+        match self {
+            Empty => ' ',
+            Horizontal => '-',
+            Vertical => '|',
+            NWSEEdge => '/',
+            NESWEdge => '\\',
+            Intersection => '+',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    West,
+    East,
+    South,
+}
+
+impl Direction {
+    fn glyph(&self) -> char {
+        match self {
+            North => '^',
+            West => '<',
+            East => '>',
+            South => 'v',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NextTurn {
+    Left,
+    Straight,
+    Right,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
+pub struct Position {
+    pub y: usize,
+    pub x: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cart {
+    position: Position,
+    direction: Direction,
+    next_turn: NextTurn,
+}
+
+impl Cart {
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn direction(&self) -> &Direction {
+        &self.direction
+    }
+
+    pub fn next_turn(&self) -> &NextTurn {
+        &self.next_turn
+    }
+
+    fn intersection(&mut self) -> &Direction {
+        self.direction = match (&self.direction, &self.next_turn) {
+            (West, Straight) | (North, Left) | (South, Right) => West,
+            (East, Straight) | (North, Right) | (South, Left) => East,
+            (North, Straight) | (West, Right) | (East, Left) => North,
+            (South, Straight) | (West, Left) | (East, Right) => South,
+        };
+
+        self.next_turn = match self.next_turn {
+            Left => Straight,
+            Straight => Right,
+            Right => Left,
+        };
+
+        &self.direction
+    }
+}
+
+/// `tick` counts full passes in reading order, so a crash on tick 14 is
+/// first visible in the frame 14 passes after the initial state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crash {
+    pub tick: usize,
+    pub position: Position,
+}
+
+pub struct Track {
+    objects: Vec<Object>,
+    carts: Vec<Option<Cart>>,
+    width: usize,
+    crashes: Vec<Position>,
+    occupancy: Vec<Option<usize>>,
+    row_counts: Vec<usize>,
+    ticks: usize,
+}
+
+impl Track {
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        s.parse()
+    }
+
+    pub fn carts(&self) -> impl Iterator<Item = &Cart> {
+        self.carts.iter().flatten()
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn tick(&mut self) -> Result<Vec<Crash>, Error> {
+        let order: Vec<_> = self
+            .occupancy
+            .chunks_exact(self.width)
+            .zip(&self.row_counts)
+            .filter(|&(_, &count)| count > 0)
+            .flat_map(|(row, _)| row.iter().flatten().copied())
+            .collect();
+        self.ticks += 1;
+        self.crashes.clear();
+
+        for i in order {
+            let from = match &self.carts[i] {
+                Some(cart) => cart.position,
+                None => continue,
+            };
+            self.occupancy[from.y * self.width + from.x] = None;
+            self.row_counts[from.y] -= 1;
+
+            let position = advance_single_cart(self, i)?;
+            let cell = &mut self.occupancy[position.y * self.width + position.x];
+
+            match cell.take() {
+                None => {
+                    *cell = Some(i);
+                    self.row_counts[position.y] += 1;
+                }
+                Some(other) => {
+                    self.row_counts[position.y] -= 1;
+                    self.crashes.push(position);
+                    self.carts[i] = None;
+                    self.carts[other] = None;
+                }
+            }
+        }
+
+        if !self.crashes.is_empty() {
+            self.carts.retain(Option::is_some);
+            self.index();
+        }
+
+        let tick = self.ticks;
+        Ok(self
+            .crashes
+            .iter()
+            .map(|&position| Crash { tick, position })
+            .collect())
+    }
+
+    fn index(&mut self) {
+        self.occupancy = vec![None; self.objects.len()];
+        self.row_counts = vec![0; self.objects.len() / self.width];
+
+        for (i, cart) in self.carts.iter().enumerate() {
+            if let Some(cart) = cart {
+                self.occupancy[cart.position.y * self.width + cart.position.x] = Some(i);
+                self.row_counts[cart.position.y] += 1;
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let mut positions: Vec<_> = self
+            .carts
+            .iter()
+            .flatten()
+            .map(|cart| cart.position)
+            .collect();
+        positions.sort_unstable();
+
+        let mut overlapping: Vec<_> = positions
+            .windows(2)
+            .filter(|pair| pair[0] == pair[1])
+            .map(|pair| pair[0].to_string())
+            .collect();
+        overlapping.dedup();
+
+        if overlapping.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Invalid(format!(
+                "overlapping carts at: {}",
+                overlapping.join(" ")
+            )))
+        }
+    }
+}
+
+impl FromStr for Track {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows: Vec<_> = s
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r').as_bytes())
+            .collect();
+
+        while let Some(&[]) = rows.last() {
+            rows.pop();
+        }
+
+        let width = match rows.iter().map(|row| row.len()).max() {
+            Some(width) if width > 0 => width,
+            _ => return Err(Error::Invalid(String::from("empty track"))),
+        };
+
+        let mut objects = Vec::with_capacity(width * rows.len());
+        let mut carts = Vec::new();
+
+        for (position, token) in rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .copied()
+                .chain(iter::repeat(b' '))
+                .take(width)
+                .enumerate()
+                .map(move |(x, token)| (Position { x, y }, token))
+        }) {
+            objects.push(match token {
+                b' ' => Empty,
+                b'-' => Horizontal,
+                b'|' => Vertical,
+                b'/' => NWSEEdge,
+                b'\\' => NESWEdge,
+                b'+' => Intersection,
+                b'^' => {
+                    carts.push(Some(Cart {
+                        position,
+                        direction: North,
+                        next_turn: Left,
+                    }));
+
+                    Vertical
+                }
+                b'v' => {
+                    carts.push(Some(Cart {
+                        position,
+                        direction: South,
+                        next_turn: Left,
+                    }));
+
+                    Vertical
+                }
+                b'<' => {
+                    carts.push(Some(Cart {
+                        position,
+                        direction: West,
+                        next_turn: Left,
+                    }));
+
+                    Horizontal
+                }
+                b'>' => {
+                    carts.push(Some(Cart {
+                        position,
+                        direction: East,
+                        next_turn: Left,
+                    }));
+
+                    Horizontal
+                }
+                inv => {
+                    return Err(Error::Invalid(format!(
+                        "invalid character: {} at position: ({}, {})",
+                        char::from(inv),
+                        position.x,
+                        position.y
+                    )))
+                }
+            });
+        }
+
+        let mut track = Track {
+            objects,
+            carts,
+            width,
+            crashes: Vec::new(),
+            occupancy: Vec::new(),
+            row_counts: Vec::new(),
+            ticks: 0,
+        };
+
+        track.validate()?;
+        track.index();
+        Ok(track)
+    }
+}
+
+pub fn render(track: &Track) -> String {
+    let mut glyphs: Vec<_> = track.objects.iter().map(Object::glyph).collect();
+
+    for cart in track.carts.iter().flatten() {
+        glyphs[cart.position.y * track.width + cart.position.x] = cart.direction.glyph();
+    }
+
+    for crash in &track.crashes {
+        glyphs[crash.y * track.width + crash.x] = 'X';
+    }
+
+    let mut frame = String::with_capacity(glyphs.len() + glyphs.len() / track.width);
+
+    for row in glyphs.chunks(track.width) {
+        frame.extend(row);
+        frame.push('\n');
+    }
+
+    frame
+}
+
+fn advance_single_cart(track: &mut Track, cart_id: usize) -> Result<Position, Error> {
+    let (width, height) = (track.width, track.objects.len() / track.width);
+    let cart = track.carts[cart_id]
+        .as_mut()
+        .expect("advancing a crashed cart");
+    let Position { x, y } = cart.position;
+
+    cart.position = match cart.direction {
+        West => x.checked_sub(1).map(|x| Position { x, y }),
+        East => Some(Position { x: x + 1, y }),
+        North => y.checked_sub(1).map(|y| Position { x, y }),
+        South => Some(Position { x, y: y + 1 }),
+    }
+    .filter(|next| next.x < width && next.y < height)
+    .ok_or(Error::OffMap(cart.position))?;
+
+    let object = &track.objects[cart.position.y * width + cart.position.x];
+
+    cart.direction = match (object, &cart.direction) {
+        (Horizontal, West) | (Horizontal, East) | (Vertical, North) | (Vertical, South) => {
+            cart.direction.clone()
+        }
+        (NWSEEdge, North) | (NESWEdge, South) => East,
+        (NWSEEdge, South) | (NESWEdge, North) => West,
+        (NWSEEdge, West) | (NESWEdge, East) => South,
+        (NWSEEdge, East) | (NESWEdge, West) => North,
+        (Intersection, _) => cart.intersection().clone(),
+        (_, direction) => {
+            return Err(Error::Derailed {
+                position: cart.position,
+                direction: direction.clone(),
+            })
+        }
+    };
+
+    Ok(cart.position)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SimOutcome {
+    pub first_crash: Crash,
+    pub last_cart: Option<Position>,
+    pub ticks: usize,
+    pub total_crashes: usize,
+    pub crash_log: Vec<Crash>,
+}
+
+impl SimOutcome {
+    fn new(crash_log: Vec<Crash>, last_cart: Option<Position>, ticks: usize) -> Self {
+        SimOutcome {
+            first_crash: crash_log[0],
+            last_cart,
+            ticks,
+            total_crashes: crash_log.len(),
+            crash_log,
+        }
+    }
+}
+
+pub fn simulate(track: Track) -> Result<SimOutcome, Error> {
+    simulate_with(track, |_, _| ())
+}
+
+pub fn simulate_with<F>(mut track: Track, mut on_tick: F) -> Result<SimOutcome, Error>
+where
+    F: FnMut(usize, &Track),
+{
+    track.validate()?;
+    track.index();
+    on_tick(track.ticks(), &track);
+
+    if track.carts().count() < 2 {
+        return Err(Error::Invalid(String::from("need at least two carts")));
+    }
+
+    let mut crash_log = Vec::new();
+
+    loop {
+        crash_log.extend(track.tick()?);
+
+        on_tick(track.ticks(), &track);
+
+        let mut carts = track.carts();
+
+        if let (first, None) = (carts.next(), carts.next()) {
+            let last_cart = first.map(Cart::position);
+
+            return Ok(SimOutcome::new(crash_log, last_cart, track.ticks()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const FIRST: &str = "\
+/->-\\        
+|   |  /----\\
+| /-+--+-\\  |
+| | |  | v  |
+\\-+-/  \\-+--/
+  \\------/   
+";
+
+    const SECOND: &str = "\
+/>-<\\  
+|   |  
+| /<+-\\
+| | | v
+\\>+</ |
+  |   ^
+  \\<->/
+";
+
+    const FIRST_CRASH: &str = "\
+/---\\        
+|   |  /----\\
+| /-+--+-\\  |
+| | |  X |  |
+\\-+-/  \\-+--/
+  \\------/   
+";
+
+    fn p(x: usize, y: usize) -> Position {
+        Position { x, y }
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // Rectangular loops that each own their rows and columns, so they only
+    // ever cross at right angles, with carts dropped onto straight pieces.
+    fn random_track(seed: u64, size: usize, loops: usize, carts: usize) -> String {
+        let mut state = seed;
+        let mut random = |n: usize| (xorshift(&mut state) % n as u64) as usize;
+        let mut grid = vec![vec![b' '; size]; size];
+        let (mut xs, mut ys): (Vec<_>, Vec<_>) = ((0..size).collect(), (0..size).collect());
+
+        for i in (1..size).rev() {
+            xs.swap(i, random(i + 1));
+            ys.swap(i, random(i + 1));
+        }
+
+        for (x, y) in xs.chunks(2).zip(ys.chunks(2)).take(loops) {
+            let (x0, x1) = (x[0].min(x[1]), x[0].max(x[1]));
+            let (y0, y1) = (y[0].min(y[1]), y[0].max(y[1]));
+
+            if x1 - x0 < 2 || y1 - y0 < 2 {
+                continue;
+            }
+
+            for &y in &[y0, y1] {
+                for cell in &mut grid[y][x0 + 1..x1] {
+                    *cell = if *cell == b'|' { b'+' } else { b'-' };
+                }
+            }
+            for row in &mut grid[y0 + 1..y1] {
+                for &x in &[x0, x1] {
+                    row[x] = if row[x] == b'-' { b'+' } else { b'|' };
+                }
+            }
+            grid[y0][x0] = b'/';
+            grid[y1][x1] = b'/';
+            grid[y0][x1] = b'\\';
+            grid[y1][x0] = b'\\';
+        }
+
+        let mut placed = 0;
+
+        while placed < carts {
+            let (x, y) = (random(size), random(size));
+
+            grid[y][x] = match (grid[y][x], random(2)) {
+                (b'-', 0) => b'<',
+                (b'-', _) => b'>',
+                (b'|', 0) => b'^',
+                (b'|', _) => b'v',
+                _ => continue,
+            };
+            placed += 1;
+        }
+
+        grid.into_iter()
+            .map(|row| String::from_utf8(row).unwrap() + "\n")
+            .collect()
+    }
+
+    type Run = Option<(Vec<Crash>, Vec<Position>)>;
+
+    // Ticks until at most one cart is left or `limit` ticks have passed,
+    // returning the crash log and the surviving carts, or None on an error.
+    fn run(input: &str, limit: usize) -> Run {
+        let mut track = Track::parse(input).ok()?;
+        let mut crashes = Vec::new();
+
+        while track.ticks() < limit && track.carts().count() > 1 {
+            crashes.extend(track.tick().ok()?);
+        }
+
+        let mut carts: Vec<_> = track.carts().map(Cart::position).collect();
+        carts.sort_unstable();
+        Some((crashes, carts))
+    }
+
+    // The simulation as it was before the occupancy grid: sort the carts
+    // every tick and track occupied cells in a map.
+    fn reference(input: &str, limit: usize) -> Run {
+        let rows: Vec<_> = input.lines().map(str::as_bytes).collect();
+        let mut carts = Vec::new();
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                let direction = match c {
+                    b'^' => (0, -1),
+                    b'v' => (0, 1),
+                    b'<' => (-1, 0),
+                    b'>' => (1, 0),
+                    _ => continue,
+                };
+                carts.push((p(x, y), direction, 0));
+            }
+        }
+
+        let mut crashes = Vec::new();
+
+        for tick in 1..=limit {
+            if carts.len() < 2 {
+                break;
+            }
+
+            carts.sort_unstable_by_key(|cart| cart.0);
+            let mut alive = vec![true; carts.len()];
+            let mut occupied: HashMap<_, _> = carts
+                .iter()
+                .enumerate()
+                .map(|(i, cart)| (cart.0, i))
+                .collect();
+
+            for i in 0..carts.len() {
+                if !alive[i] {
+                    continue;
+                }
+
+                let (position, (dx, dy), turns) = &mut carts[i];
+                occupied.remove(position);
+
+                let x = (position.x as isize + *dx) as usize;
+                let y = (position.y as isize + *dy) as usize;
+                let glyph = *rows.get(y)?.get(x)?;
+
+                let (new_dx, new_dy) = match glyph {
+                    b'-' | b'<' | b'>' if *dy == 0 => (*dx, *dy),
+                    b'|' | b'^' | b'v' if *dx == 0 => (*dx, *dy),
+                    b'/' => (-*dy, -*dx),
+                    b'\\' => (*dy, *dx),
+                    b'+' => {
+                        *turns += 1;
+                        match *turns % 3 {
+                            1 => (*dy, -*dx),
+                            2 => (*dx, *dy),
+                            _ => (-*dy, *dx),
+                        }
+                    }
+                    _ => return None,
+                };
+
+                *position = p(x, y);
+                *dx = new_dx;
+                *dy = new_dy;
+
+                match occupied.remove(position) {
+                    Some(j) => {
+                        alive[i] = false;
+                        alive[j] = false;
+                        crashes.push(Crash {
+                            tick,
+                            position: *position,
+                        });
+                    }
+                    None => {
+                        occupied.insert(*position, i);
+                    }
+                }
+            }
+
+            carts = carts
+                .into_iter()
+                .zip(alive)
+                .filter(|&(_, alive)| alive)
+                .map(|(cart, _)| cart)
+                .collect();
+        }
+
+        let mut carts: Vec<_> = carts.into_iter().map(|cart| cart.0).collect();
+        carts.sort_unstable();
+        Some((crashes, carts))
+    }
+
+    #[test]
+    fn first_example() {
+        let outcome = simulate(Track::parse(FIRST).unwrap()).unwrap();
+
+        assert_eq!(outcome.first_crash.position, p(7, 3));
+        assert_eq!(outcome.last_cart, None);
+    }
+
+    #[test]
+    fn second_example() {
+        let track = Track::parse(SECOND).unwrap();
+        assert_eq!(track.carts().count(), 9);
+
+        let outcome = simulate(track).unwrap();
+        assert_eq!(outcome.first_crash.position, p(2, 0));
+        assert_eq!(outcome.last_cart, Some(p(6, 4)));
+        assert_eq!(outcome.ticks, 3);
+    }
+
+    fn error(track: &str) -> Error {
+        simulate(Track::parse(track).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn derailed_onto_empty() {
+        let error = error("->- -\n->--------\n");
+
+        assert!(
+            matches!(
+                error,
+                Error::Derailed {
+                    position: Position { x: 3, y: 0 },
+                    direction: East
+                }
+            ),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().contains("3,0"), "{}", error);
+    }
+
+    #[test]
+    fn derailed_across_track() {
+        let error = error("|v|\n|-|\n|^|\n");
+
+        assert!(
+            matches!(
+                error,
+                Error::Derailed {
+                    position: Position { x: 1, y: 1 },
+                    direction: South
+                }
+            ),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().contains("1,1"), "{}", error);
+    }
+
+    #[test]
+    fn off_map() {
+        let error = error("<-\n<-\n");
+
+        assert!(
+            matches!(error, Error::OffMap(Position { x: 0, y: 0 })),
+            "{:?}",
+            error
+        );
+        assert!(error.to_string().contains("0,0"), "{}", error);
+    }
+
+    #[test]
+    fn overlapping_carts() {
+        let mut track = Track::parse("->>-\n----\n").unwrap();
+        let cart = track.carts[0].clone();
+        track.carts.push(cart);
+
+        let error = track.validate().unwrap_err().to_string();
+        assert!(error.contains("1,0") && !error.contains("2,0"), "{}", error);
+        assert!(matches!(simulate(track), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn separate_carts_validate() {
+        for &input in &[FIRST, SECOND, "->>-\n----\n"] {
+            assert!(Track::parse(input).unwrap().validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn render_parsed_examples() {
+        assert_eq!(render(&Track::parse(FIRST).unwrap()), FIRST);
+        assert_eq!(render(&Track::parse(SECOND).unwrap()), SECOND);
+    }
+
+    #[test]
+    fn straight_line_frames() {
+        let mut frames = Vec::new();
+        simulate_with(
+            Track::parse("|\nv\n|\n|\n|\n^\n|\n").unwrap(),
+            |tick, track| frames.push((tick, render(track))),
+        )
+        .unwrap();
+
+        assert_eq!(
+            frames,
+            [
+                (0, String::from("|\nv\n|\n|\n|\n^\n|\n")),
+                (1, String::from("|\n|\nv\n|\n^\n|\n|\n")),
+                (2, String::from("|\n|\n|\nX\n|\n|\n|\n")),
+            ]
+        );
+    }
+
+    #[test]
+    fn final_frame() {
+        let mut last = String::new();
+        simulate_with(Track::parse(FIRST).unwrap(), |_, track| {
+            last = render(track)
+        })
+        .unwrap();
+
+        assert_eq!(last, FIRST_CRASH);
+    }
+
+    #[test]
+    fn reference_agrees_on_examples() {
+        for &input in &[FIRST, SECOND] {
+            assert_eq!(run(input, 100), reference(input, 100));
+        }
+    }
+
+    #[test]
+    fn occupancy_matches_reference() {
+        let mut compared = 0;
+
+        for seed in 1..=200 {
+            let input = random_track(seed, 60, 12, 100);
+            let expected = reference(&input, 2000);
+
+            assert_eq!(run(&input, 2000), expected, "seed {}:\n{}", seed, input);
+            compared += expected.is_some() as usize;
+        }
+
+        assert!(compared > 100, "only {} tracks ran cleanly", compared);
+    }
+
+    #[test]
+    fn large_track() {
+        let input = random_track(7, 512, 120, 2000);
+        let expected = reference(&input, 500);
+
+        assert_eq!(Track::parse(&input).unwrap().carts().count(), 2000);
+        assert_eq!(run(&input, 500), expected);
+    }
+
+    #[test]
+    fn second_example_crash_log() {
+        let outcome = simulate(Track::parse(SECOND).unwrap()).unwrap();
+        let crash = |tick, x, y| Crash {
+            tick,
+            position: p(x, y),
+        };
+
+        assert_eq!(
+            outcome.crash_log,
+            [
+                crash(1, 2, 0),
+                crash(1, 2, 4),
+                crash(1, 6, 4),
+                crash(3, 2, 4)
+            ]
+        );
+        assert_eq!(outcome.total_crashes, 4);
+        assert_eq!(outcome.ticks, 3);
+    }
+
+    #[test]
+    fn crashed_carts_are_dropped() {
+        let mut track = Track::parse(SECOND).unwrap();
+
+        assert_eq!(track.tick().unwrap().len(), 3);
+        assert_eq!(track.carts.len(), 3);
+        assert!(track.carts.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn trimmed_right_margins() {
+        for &input in &[FIRST, SECOND] {
+            let expected = simulate(Track::parse(input).unwrap()).unwrap();
+            let trimmed: String = input
+                .lines()
+                .map(|line| line.trim_end().to_owned() + "\n")
+                .collect();
+
+            assert_ne!(trimmed, input);
+            assert_eq!(render(&Track::parse(&trimmed).unwrap()), input);
+            assert_eq!(simulate(Track::parse(&trimmed).unwrap()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn crlf_and_missing_newline() {
+        for &input in &[FIRST, SECOND] {
+            let expected = simulate(Track::parse(input).unwrap()).unwrap();
+            let crlf = input.replace('\n', "\r\n");
+
+            assert_eq!(simulate(Track::parse(&crlf).unwrap()).unwrap(), expected);
+            assert_eq!(
+                simulate(Track::parse(input.trim_end_matches('\n')).unwrap()).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn empty_track() {
+        assert!(matches!(Track::parse("\n\n"), Err(Error::Invalid(_))));
+        assert!(matches!(Track::parse(""), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn two_crashes_in_one_tick() {
+        let outcome = simulate(Track::parse(">-<-\n>-<-\n->--\n").unwrap()).unwrap();
+
+        assert_eq!(
+            outcome.crash_log,
+            [
+                Crash {
+                    tick: 1,
+                    position: p(1, 0)
+                },
+                Crash {
+                    tick: 1,
+                    position: p(1, 1)
+                },
+            ]
+        );
+        assert_eq!(outcome.last_cart, Some(p(2, 2)));
+        assert_eq!(outcome.ticks, 1);
+    }
+
+    #[test]
+    fn last_two_collide_within_the_tick() {
+        // The first crash leaves two carts, which meet two sub-steps later.
+        let outcome = simulate(Track::parse(">-<-\n>-<-\n").unwrap()).unwrap();
+
+        assert_eq!(outcome.total_crashes, 2);
+        assert_eq!(outcome.last_cart, None);
+        assert_eq!(outcome.ticks, 1);
+    }
+
+    #[test]
+    fn last_cart_moves_after_the_crash() {
+        // The survivor comes after the crash in reading order and must still
+        // finish its move before the tick ends.
+        let outcome = simulate(Track::parse(">-<--\n-->--\n").unwrap()).unwrap();
+
+        assert_eq!(outcome.first_crash.position, p(1, 0));
+        assert_eq!(outcome.last_cart, Some(p(3, 1)));
+        assert_eq!(outcome.ticks, 1);
+    }
+
+    #[test]
+    fn single_cart() {
+        assert!(matches!(
+            simulate(Track::parse("->--\n").unwrap()),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    fn state(track: &Track) -> Vec<(Position, Direction, NextTurn)> {
+        track
+            .carts()
+            .map(|cart| {
+                (
+                    cart.position(),
+                    cart.direction().clone(),
+                    cart.next_turn().clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_three_ticks() {
+        let mut track = Track::parse(FIRST).unwrap();
+
+        assert!(track.tick().unwrap().is_empty());
+        assert_eq!(
+            state(&track),
+            [(p(3, 0), East, Left), (p(9, 4), East, Straight)]
+        );
+        assert!(track.tick().unwrap().is_empty());
+        assert_eq!(
+            state(&track),
+            [(p(4, 0), South, Left), (p(10, 4), East, Straight)]
+        );
+        assert!(track.tick().unwrap().is_empty());
+        assert_eq!(
+            state(&track),
+            [(p(4, 1), South, Left), (p(11, 4), East, Straight)]
+        );
+        assert_eq!(track.ticks(), 3);
+    }
+
+    #[test]
+    fn first_crash_on_tick_14() {
+        let mut track = Track::parse(FIRST).unwrap();
+
+        for _ in 0..13 {
+            assert!(track.tick().unwrap().is_empty());
+        }
+        assert_eq!(
+            track.tick().unwrap(),
+            [Crash {
+                tick: 14,
+                position: p(7, 3)
+            }]
+        );
+
+        let outcome = simulate(Track::parse(FIRST).unwrap()).unwrap();
+        assert_eq!(outcome.first_crash.tick, 14);
+        assert_eq!(outcome.ticks, 14);
+    }
+}
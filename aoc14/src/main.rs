@@ -1,69 +1,538 @@
-const INPUT: usize = 320_851;
-const INPUT_ARRAY: [u8; 6] = [3, 2, 0, 8, 5, 1];
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Error as IoError;
+use std::path::Path;
 
-fn part_one() {
-    let mut recipes: Vec<u8> = vec![3, 7];
-    let mut first = 0;
-    let mut second = 1;
+const WINDOW: usize = 10;
+const MAX_RECIPES: usize = 1_000_000_000;
 
-    while recipes.len() < INPUT + 10 {
-        let sum = recipes[first] + recipes[second];
+#[derive(Debug)]
+enum Error {
+    Io(IoError),
+    Invalid(String),
+    NotFound { searched: usize },
+    Usage(String),
+}
 
-        if sum < 10 {
-            recipes.push(sum);
-        } else {
-            recipes.push(1);
-            recipes.push(sum - 10);
-        }
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
 
-        first = (first + (recipes[first] + 1) as usize) % recipes.len();
-        second = (second + (recipes[second] + 1) as usize) % recipes.len();
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(s) => write!(f, "invalid input: {}", s),
+            Error::NotFound { searched } => {
+                write!(f, "pattern not found in the first {} recipes", searched)
+            }
+            Error::Usage(s) => write!(f, "{}", s),
+        }
     }
+}
+
+struct Input {
+    count: usize,
+    // Taken from the raw text rather than `count`, so leading zeros survive.
+    digits: Vec<u8>,
+}
 
-    print!("Part 1: ");
-    for x in recipes.iter().skip(INPUT).take(10).cloned() {
-        print!("{}", x);
+fn parse_digits(s: &str) -> Result<Input, Error> {
+    let s = s.trim();
+
+    if s.is_empty() {
+        return Err(Error::Invalid(String::from("no digits")));
     }
-    println!();
+
+    let digits = s
+        .bytes()
+        .map(|b| match b {
+            b'0'..=b'9' => Ok(b - b'0'),
+            _ => Err(Error::Invalid(format!("not a digit: {:?}", char::from(b)))),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let count = s
+        .parse()
+        .map_err(|_| Error::Invalid(format!("recipe count out of range: {}", s)))?;
+
+    Ok(Input { count, digits })
 }
 
-fn compare(recipes: &[u8]) -> bool {
-    INPUT_ARRAY == recipes[recipes.len() - INPUT_ARRAY.len()..]
+fn parse_input(path: &Path) -> Result<Input, Error> {
+    parse_digits(&fs::read_to_string(path)?)
 }
 
-fn part_two() {
-    let mut recipes: Vec<u8> = vec![3, 7, 1, 0, 1, 0, 1, 2, 4, 5];
-    let mut first = 6;
-    let mut second = 3;
+struct RecipeStream {
+    board: Vec<u8>,
+    elves: Vec<usize>,
+    next: usize,
+}
 
-    loop {
-        let sum = recipes[first] + recipes[second];
-        if sum < 10 {
-            recipes.push(sum);
+impl RecipeStream {
+    fn new() -> Self {
+        RecipeStream::with_elves(&[3, 7])
+    }
+
+    /// One elf starts on each of the `initial` recipes.
+    fn with_elves(initial: &[u8]) -> Self {
+        RecipeStream {
+            board: initial.to_vec(),
+            elves: (0..initial.len()).collect(),
+            next: 0,
+        }
+    }
 
-            if compare(&recipes) {
+    fn step(&mut self) {
+        let board = &mut self.board;
+        let mut sum: usize = self.elves.iter().map(|&elf| board[elf] as usize).sum();
+        let mut digits = [0; 20];
+        let mut start = digits.len();
+
+        loop {
+            start -= 1;
+            digits[start] = (sum % 10) as u8;
+            sum /= 10;
+
+            if sum == 0 {
                 break;
             }
-        } else {
-            recipes.push(1);
-            if compare(&recipes) {
-                break;
+        }
+
+        board.extend_from_slice(&digits[start..]);
+
+        // The elves outrun the board's growth and wrap back to its start,
+        // so the whole board stays live and no prefix can be dropped.
+        for elf in &mut self.elves {
+            *elf = (*elf + board[*elf] as usize + 1) % board.len();
+        }
+    }
+}
+
+impl Iterator for RecipeStream {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.next >= self.board.len() {
+            self.step();
+        }
+
+        self.next += 1;
+        Some(self.board[self.next - 1])
+    }
+}
+
+fn scores_after(count: usize, window: usize) -> Result<String, Error> {
+    let end = count
+        .checked_add(window)
+        .ok_or_else(|| Error::Invalid(format!("{} + {} recipes overflows", count, window)))?;
+
+    Ok(RecipeStream::new()
+        .take(end)
+        .skip(count)
+        .map(|x| char::from(b'0' + x))
+        .collect())
+}
+
+struct Matcher<'a> {
+    pattern: &'a [u8],
+    failure: Vec<usize>,
+    state: usize,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(pattern: &'a [u8]) -> Self {
+        let mut failure = vec![0; pattern.len()];
+        let mut k = 0;
+
+        for i in 1..pattern.len() {
+            while k > 0 && pattern[i] != pattern[k] {
+                k = failure[k - 1];
             }
 
-            recipes.push(sum - 10);
-            if compare(&recipes) {
-                break;
+            if pattern[i] == pattern[k] {
+                k += 1;
             }
+
+            failure[i] = k;
+        }
+
+        Matcher {
+            pattern,
+            failure,
+            state: 0,
         }
+    }
+
+    fn push(&mut self, digit: u8) -> bool {
+        while self.state > 0 && self.pattern[self.state] != digit {
+            self.state = self.failure[self.state - 1];
+        }
+
+        if self.pattern[self.state] == digit {
+            self.state += 1;
+        }
+
+        if self.state == self.pattern.len() {
+            self.state = self.failure[self.state - 1];
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn first_occurrence(pattern: &[u8], max_recipes: Option<usize>) -> Result<usize, Error> {
+    let mut matcher = Matcher::new(pattern);
+    let searched = max_recipes.unwrap_or(usize::MAX);
+
+    RecipeStream::new()
+        .take(searched)
+        .position(|digit| matcher.push(digit))
+        .map(|end| end + 1 - pattern.len())
+        .ok_or(Error::NotFound { searched })
+}
+
+fn parse_flag(flag: &str, value: Option<String>) -> Result<usize, Error> {
+    let value = value.ok_or_else(|| Error::Usage(format!("{} needs a value", flag)))?;
+
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value for {}: {}", flag, value)))
+}
+
+fn main() -> Result<(), Error> {
+    let path = Path::new("inputs/input-14-01.txt");
+
+    let mut args = env::args().skip(1);
+    let (mut input, mut max_recipes) = (None, MAX_RECIPES);
 
-        first = (first + (recipes[first] + 1) as usize) % recipes.len();
-        second = (second + (recipes[second] + 1) as usize) % recipes.len();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-recipes" => max_recipes = parse_flag(&arg, args.next())?,
+            _ if !arg.starts_with("--") && input.is_none() => input = Some(parse_digits(&arg)?),
+            _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
+        }
     }
 
-    println!("Part 2: {}", recipes.len() - INPUT_ARRAY.len());
+    let input = match input {
+        Some(input) => input,
+        None => parse_input(path)?,
+    };
+
+    println!("Part 1: {}", scores_after(input.count, WINDOW)?);
+    println!(
+        "Part 2: {}",
+        first_occurrence(&input.digits, Some(max_recipes))?
+    );
+
+    Ok(())
 }
 
-fn main() {
-    part_one();
-    part_two();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn digits_and_count() {
+        let input = parse_digits("59414").unwrap();
+
+        assert_eq!(input.count, 59414);
+        assert_eq!(input.digits, [5, 9, 4, 1, 4]);
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let path = env::temp_dir().join("aoc14-input.txt");
+        fs::write(&path, "320851\n").unwrap();
+        let input = parse_input(&path).unwrap();
+
+        assert_eq!(input.count, 320_851);
+        assert_eq!(input.digits, [3, 2, 0, 8, 5, 1]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(matches!(parse_digits("12a4"), Err(Error::Invalid(_))));
+        assert!(matches!(parse_digits("-5"), Err(Error::Invalid(_))));
+        assert!(matches!(parse_digits("\n"), Err(Error::Invalid(_))));
+        assert!(matches!(
+            parse_digits("99999999999999999999999"),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    // Compares the board's suffix against the pattern after every push, as
+    // part two did before the matcher.
+    fn naive_occurrence(pattern: &[u8]) -> usize {
+        let mut board = Vec::new();
+
+        for digit in RecipeStream::new() {
+            board.push(digit);
+
+            if board.ends_with(pattern) {
+                return board.len() - pattern.len();
+            }
+        }
+
+        unreachable!()
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn matcher_matches_naive_on_examples() {
+        for pattern in &[
+            [5, 1, 5, 8, 9],
+            [0, 1, 2, 4, 5],
+            [9, 2, 5, 1, 0],
+            [5, 9, 4, 1, 4],
+        ] {
+            assert_eq!(
+                first_occurrence(pattern, None).unwrap(),
+                naive_occurrence(pattern)
+            );
+        }
+    }
+
+    #[test]
+    fn matcher_matches_naive_on_long_patterns() {
+        let stream: Vec<_> = RecipeStream::new().take(50_000).collect();
+        let mut state = 0x2545_f491_4f6c_dd1d;
+
+        for _ in 0..20 {
+            let start = (xorshift(&mut state) % 40_000) as usize;
+            let pattern = &stream[start..start + 12];
+
+            assert_eq!(
+                first_occurrence(pattern, None).unwrap(),
+                naive_occurrence(pattern)
+            );
+        }
+    }
+
+    #[test]
+    fn matcher_on_self_overlapping_patterns() {
+        let mut state = 9;
+
+        for _ in 0..2000 {
+            let len = 1 + (xorshift(&mut state) % 8) as usize;
+            let pattern: Vec<_> = (0..len).map(|_| (xorshift(&mut state) % 2) as u8).collect();
+            let text: Vec<_> = (0..200).map(|_| (xorshift(&mut state) % 2) as u8).collect();
+            let mut matcher = Matcher::new(&pattern);
+
+            let found: Vec<_> = text
+                .iter()
+                .enumerate()
+                .filter(|&(_, &digit)| matcher.push(digit))
+                .map(|(i, _)| i + 1 - len)
+                .collect();
+            let expected: Vec<_> = (len..=text.len())
+                .filter(|&end| text[..end].ends_with(&pattern))
+                .map(|end| end - len)
+                .collect();
+
+            assert_eq!(found, expected, "{:?} in {:?}", pattern, text);
+        }
+    }
+
+    #[test]
+    fn real_input() {
+        let input = parse_input(Path::new("../inputs/input-14-01.txt")).unwrap();
+
+        assert_eq!(scores_after(input.count, WINDOW).unwrap(), "7116398711");
+        assert_eq!(
+            first_occurrence(&input.digits, Some(MAX_RECIPES)).unwrap(),
+            20_316_365
+        );
+    }
+
+    #[test]
+    fn elves_revisit_the_start_of_the_board() {
+        let mut stream = RecipeStream::new();
+
+        while stream.board.len() < 200_000 {
+            stream.step();
+        }
+
+        let mut lowest = usize::MAX;
+
+        while stream.board.len() < 400_000 {
+            stream.step();
+            lowest = lowest.min(*stream.elves.iter().min().unwrap());
+        }
+
+        // Long after the board passed 200k digits the elves still land in
+        // its first few entries, so no prefix can be discarded.
+        assert!(lowest < 20, "lowest elf index {}", lowest);
+    }
+
+    #[test]
+    fn published_scores() {
+        assert_eq!(scores_after(9, WINDOW).unwrap(), "5158916779");
+        assert_eq!(scores_after(5, WINDOW).unwrap(), "0124515891");
+        assert_eq!(scores_after(18, WINDOW).unwrap(), "9251071085");
+        assert_eq!(scores_after(2018, WINDOW).unwrap(), "5941429882");
+    }
+
+    #[test]
+    fn published_occurrences() {
+        assert_eq!(first_occurrence(&[5, 1, 5, 8, 9], None).unwrap(), 9);
+        assert_eq!(first_occurrence(&[0, 1, 2, 4, 5], None).unwrap(), 5);
+        assert_eq!(first_occurrence(&[9, 2, 5, 1, 0], None).unwrap(), 18);
+        assert_eq!(first_occurrence(&[5, 9, 4, 1, 4], None).unwrap(), 2018);
+    }
+
+    #[test]
+    fn pattern_at_the_start() {
+        assert_eq!(first_occurrence(&[3, 7], None).unwrap(), 0);
+        assert_eq!(scores_after(0, 2).unwrap(), "37");
+    }
+
+    #[test]
+    fn leading_zero_pattern() {
+        let input = parse_digits("01245").unwrap();
+
+        assert_eq!(input.count, 1245);
+        assert_eq!(input.digits, [0, 1, 2, 4, 5]);
+        assert_eq!(first_occurrence(&input.digits, None).unwrap(), 5);
+    }
+
+    fn digits(stream: RecipeStream, n: usize) -> String {
+        stream.take(n).map(|x| char::from(b'0' + x)).collect()
+    }
+
+    #[test]
+    fn walkthrough_stream() {
+        // The walkthrough's 20 recipes, then the ten after 18 from the
+        // third part-one example.
+        assert_eq!(
+            digits(RecipeStream::new(), 28),
+            "3710101245158916779251071085"
+        );
+    }
+
+    #[test]
+    fn three_elves() {
+        // 3 + 7 + 1 = 11, then all three elves land on the last 1 and add
+        // 3, then all wrap to the first 3 and add 9, and so on.
+        assert_eq!(
+            digits(RecipeStream::with_elves(&[3, 7, 1]), 25),
+            "3711139327273272121693273"
+        );
+    }
+
+    #[test]
+    fn three_digit_sum() {
+        let stream = RecipeStream::with_elves(&[9; 12]);
+
+        assert_eq!(digits(stream, 15), "999999999999108");
+    }
+
+    #[test]
+    fn two_elves_by_default() {
+        assert_eq!(
+            digits(RecipeStream::with_elves(&[3, 7]), 20),
+            digits(RecipeStream::new(), 20)
+        );
+    }
+
+    #[test]
+    fn window_lengths() {
+        assert_eq!(scores_after(9, 1).unwrap(), "5");
+        assert_eq!(scores_after(9, 20).unwrap(), "51589167792510710851");
+        assert_eq!(scores_after(9, 0).unwrap(), "");
+    }
+
+    #[test]
+    fn window_overflow() {
+        assert!(matches!(
+            scores_after(usize::MAX, 10),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            scores_after(10, usize::MAX),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn pattern_not_found() {
+        let pattern = [0; 10];
+        let recipes: Vec<_> = RecipeStream::new().take(100_000).collect();
+        assert!(!recipes.windows(10).any(|window| window == pattern));
+
+        assert!(matches!(
+            first_occurrence(&pattern, Some(100_000)),
+            Err(Error::NotFound { searched: 100_000 })
+        ));
+    }
+
+    #[test]
+    fn limit_is_inclusive_of_the_match() {
+        // 59414 ends at recipe 2023.
+        assert_eq!(
+            first_occurrence(&[5, 9, 4, 1, 4], Some(2023)).unwrap(),
+            2018
+        );
+        assert!(matches!(
+            first_occurrence(&[5, 9, 4, 1, 4], Some(2022)),
+            Err(Error::NotFound { searched: 2022 })
+        ));
+    }
+
+    #[test]
+    fn max_recipes_flag() {
+        assert_eq!(
+            parse_flag("--max-recipes", Some("500".to_string())).unwrap(),
+            500
+        );
+        assert!(matches!(
+            parse_flag("--max-recipes", None),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn matcher_speedup() {
+        let input = parse_input(Path::new("../inputs/input-14-01.txt")).unwrap();
+        let pattern = &input.digits[..];
+
+        // Generating recipes costs the same either way and dominates the
+        // run time, so generate them once and time only the matching.
+        let digits: Vec<u8> = RecipeStream::new()
+            .take(20_316_365 + pattern.len())
+            .collect();
+
+        let start = Instant::now();
+        let mut board = Vec::with_capacity(digits.len());
+        let naive = digits.iter().position(|&digit| {
+            board.push(digit);
+            board.ends_with(pattern)
+        });
+        let naive_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut matcher = Matcher::new(pattern);
+        let fast = digits.iter().position(|&digit| matcher.push(digit));
+        let fast_time = start.elapsed();
+
+        assert_eq!(fast, naive);
+        assert_eq!(fast.unwrap() + 1 - pattern.len(), 20_316_365);
+        assert!(
+            fast_time * 2 < naive_time,
+            "matcher took {:?}, slice comparison took {:?}",
+            fast_time,
+            naive_time
+        );
+    }
 }
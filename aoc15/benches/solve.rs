@@ -0,0 +1,43 @@
+use aoc15::solve;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const EXAMPLES: [&str; 6] = [
+    "#######\n#.G...#\n#...EG#\n#.#.#G#\n#..G#E#\n#.....#\n#######\n",
+    "#######\n#G..#E#\n#E#E.E#\n#G.##.#\n#...#E#\n#...E.#\n#######\n",
+    "#######\n#E..EG#\n#.#G.E#\n#E.##E#\n#G..#.#\n#..E#.#\n#######\n",
+    "#######\n#E.G#.#\n#.#G..#\n#G.#.G#\n#G..#.#\n#...E.#\n#######\n",
+    "#######\n#.E...#\n#.#..G#\n#.###.#\n#E#G#G#\n#...#G#\n#######\n",
+    "#########\n#G......#\n#.E.#...#\n#..##..G#\n#...##..#\n#...#...#\n#.G...G.#\n#.....G.#\n#########\n",
+];
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/input-15-01.txt");
+
+/// Runs `solve` until at least a second has passed and reports the mean time.
+fn bench(name: &str, input: &str) {
+    let mut iterations = 0u32;
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(1) {
+        solve(input).unwrap();
+        iterations += 1;
+    }
+
+    println!(
+        "{:<12} {:>8} iterations {:>12.3?}/iter",
+        name,
+        iterations,
+        start.elapsed() / iterations
+    );
+}
+
+fn main() {
+    for (i, example) in EXAMPLES.iter().enumerate() {
+        bench(&format!("example {}", i + 1), example);
+    }
+
+    match fs::read_to_string(INPUT) {
+        Ok(input) => bench("real input", &input),
+        Err(e) => println!("skipping real input: {}", e),
+    }
+}
@@ -0,0 +1,1105 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Error as IoError;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+use self::Action::*;
+use self::Direction::*;
+use self::Entity::*;
+use self::Target::*;
+
+const UNREACHABLE: u16 = u16::MAX;
+const DIRECTIONS: [Direction; 4] = [North, West, East, South];
+const HP: u8 = 200;
+const AP: u8 = 3;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Invalid(String),
+    Usage(String),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(error: &str) -> Self {
+        Error::Invalid(error.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(error: String) -> Self {
+        Error::Invalid(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(s) => write!(f, "invalid input: {}", s),
+            Error::Usage(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
+struct HitPoints(u8);
+
+impl HitPoints {
+    fn hit(&mut self, damage: u8) -> bool {
+        match self.0.overflowing_sub(damage) {
+            (0, _) | (_, true) => {
+                self.0 = 0;
+                true
+            }
+            (new, _) => {
+                self.0 = new;
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Unit {
+    id: usize,
+    hp: HitPoints,
+}
+
+#[derive(Debug, Clone)]
+enum Entity {
+    Empty,
+    Wall,
+    Elf(Unit),
+    Goblin(Unit),
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Empty => write!(f, "Empty Space"),
+            Wall => write!(f, "Wall"),
+            Elf(unit) => write!(f, "Elf with {} hit points", unit.hp.0),
+            Goblin(unit) => write!(f, "Goblin with {} hit points", unit.hp.0),
+        }
+    }
+}
+
+impl Entity {
+    fn die(&mut self) {
+        *self = Empty;
+    }
+}
+
+enum Direction {
+    North,
+    West,
+    East,
+    South,
+}
+
+enum Target {
+    Found(Position),
+    Unreachable,
+}
+#[derive(Debug)]
+enum Action {
+    Wait,
+    Attack(Position),
+    Move,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Position(pub usize);
+
+impl Position {
+    fn to(self, width: usize, direction: &Direction) -> Option<Self> {
+        match direction {
+            North => self.0.checked_sub(width).map(Position),
+            West if !self.0.is_multiple_of(width) => self.0.checked_sub(1).map(Position),
+            East if self.0 % width != width - 1 => self.0.checked_add(1).map(Position),
+            South => self.0.checked_add(width).map(Position),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Faction {
+    Elves,
+    Goblins,
+}
+
+impl fmt::Display for Faction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Faction::Elves => write!(f, "Elves"),
+            Faction::Goblins => write!(f, "Goblins"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub full_rounds: u32,
+    pub remaining_hp: u32,
+    pub winner: Faction,
+}
+
+impl Outcome {
+    pub fn score(&self) -> u32 {
+        self.full_rounds * self.remaining_hp
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RoundResult {
+    /// Every living unit took its turn.
+    Complete,
+    /// A unit started its turn with no enemies left.
+    Over(Faction),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Config {
+    pub elf_ap: u8,
+    pub goblin_ap: u8,
+    pub starting_hp: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            elf_ap: AP,
+            goblin_ap: AP,
+            starting_hp: HP,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    entities: Vec<Entity>,
+    width: usize,
+    // set once the first round is played, so `round` knows when to apply
+    // `cfg.starting_hp`
+    started: bool,
+}
+
+/// Buffers reused by every turn of every round; owned by the caller so they
+/// aren't part of a `Board`'s cloned state.
+#[derive(Debug, Default)]
+pub struct Scratch {
+    turn_order: Vec<(Position, usize)>,
+    pathfinding: Vec<u16>,
+}
+
+impl FromStr for Board {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows: Vec<_> = s
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r').as_bytes())
+            .collect();
+
+        while let Some(&[]) = rows.last() {
+            rows.pop();
+        }
+
+        let width = match rows.first() {
+            Some(row) if !row.is_empty() => row.len(),
+            _ => return Err("empty board".into()),
+        };
+
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(
+                    format!("line {} is {} wide, expected {}", y + 1, row.len(), width).into(),
+                );
+            }
+
+            if let Some(&x) = row
+                .iter()
+                .find(|&x| !matches!(x, b'.' | b'#' | b'G' | b'E'))
+            {
+                return Err(
+                    format!("invalid character on line {}: {}", y + 1, char::from(x)).into(),
+                );
+            }
+        }
+
+        let mut entities = Vec::with_capacity(width * rows.len());
+        let mut units = 0..;
+        let mut unit = || Unit {
+            id: units.next().unwrap(),
+            hp: HitPoints(HP),
+        };
+
+        entities.extend(rows.iter().flat_map(|row| row.iter()).map(|&x| match x {
+            b'.' => Empty,
+            b'#' => Wall,
+            b'G' => Goblin(unit()),
+            _ => Elf(unit()),
+        }));
+
+        Ok(Board {
+            entities,
+            width,
+            started: false,
+        })
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in self.entities.chunks_exact(self.width) {
+            for x in y.iter() {
+                match x {
+                    Goblin(_) => write!(f, "G"),
+                    Elf(_) => write!(f, "E"),
+                    Wall => write!(f, "#"),
+                    Empty => write!(f, "."),
+                }?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Index<Position> for [u16] {
+    type Output = u16;
+
+    fn index(&self, index: Position) -> &Self::Output {
+        &self[index.0]
+    }
+}
+
+impl IndexMut<Position> for [u16] {
+    fn index_mut(&mut self, index: Position) -> &mut Self::Output {
+        &mut self[index.0]
+    }
+}
+
+impl Index<Position> for Vec<Entity> {
+    type Output = Entity;
+
+    fn index(&self, index: Position) -> &Self::Output {
+        &self[index.0]
+    }
+}
+
+impl IndexMut<Position> for Vec<Entity> {
+    fn index_mut(&mut self, index: Position) -> &mut Self::Output {
+        &mut self[index.0]
+    }
+}
+
+impl Board {
+    fn calculate_turn_order(&self, turn_order: &mut Vec<(Position, usize)>) {
+        turn_order.clear();
+
+        turn_order.extend(self.entities.iter().enumerate().filter_map(
+            |(i, entity)| match entity {
+                Elf(unit) | Goblin(unit) => Some((Position(i), unit.id)),
+                _ => None,
+            },
+        ));
+    }
+
+    fn move_to(&mut self, old: Position, new: Position) {
+        self.entities.swap(old.0, new.0);
+    }
+
+    /// The attacker is whichever faction the victim isn't.
+    fn attack(&mut self, position: Position, cfg: &Config) {
+        let victim = &mut self.entities[position];
+
+        let dead = match victim {
+            Goblin(unit) => unit.hp.hit(cfg.elf_ap),
+            Elf(unit) => unit.hp.hit(cfg.goblin_ap),
+            entity => panic!("attempting to attack {}", entity,),
+        };
+
+        if dead {
+            victim.die();
+        }
+    }
+
+    fn pick_action(&self, position: Position) -> Action {
+        let elf = match self.entities[position] {
+            Elf(_) => true,
+            Goblin(_) => false,
+            // target died before the end of turn
+            _ => return Wait,
+        };
+
+        let mut can_move = false;
+
+        let target = DIRECTIONS
+            .iter()
+            .filter_map(|direction| {
+                match (
+                    position
+                        .to(self.width, direction)
+                        .map(|position| (position, &self.entities[position])),
+                    elf,
+                ) {
+                    (Some((position, Goblin(unit))), true)
+                    | (Some((position, Elf(unit))), false) => Some((&unit.hp, position)),
+                    (Some((_, Empty)), _) => {
+                        can_move = true;
+                        None
+                    }
+                    _ => None,
+                }
+            })
+            .min_by_key(|&(hp, position)| (hp.0, position.0))
+            .map(|(_, position)| position);
+
+        match (target, can_move) {
+            (Some(position), _) => Attack(position),
+            (None, true) => Move,
+            _ => Wait,
+        }
+    }
+
+    /// Breadth-first distances from `start` through empty squares.
+    fn update_paths(&self, start: Position, pathfinding: &mut [u16]) {
+        for x in pathfinding.iter_mut() {
+            *x = UNREACHABLE;
+        }
+
+        let mut queue = VecDeque::new();
+        pathfinding[start] = 0;
+        queue.push_back(start);
+
+        while let Some(position) = queue.pop_front() {
+            let distance = pathfinding[position] + 1;
+
+            for direction in DIRECTIONS.iter() {
+                match position
+                    .to(self.width, direction)
+                    .map(|position| (position, &self.entities[position]))
+                {
+                    Some((next, Empty)) if pathfinding[next] == UNREACHABLE => {
+                        pathfinding[next] = distance;
+                        queue.push_back(next);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn count(&self, faction: Faction) -> usize {
+        self.entities
+            .iter()
+            .filter(|entity| match faction {
+                Faction::Elves => matches!(entity, Elf(_)),
+                Faction::Goblins => matches!(entity, Goblin(_)),
+            })
+            .count()
+    }
+
+    fn enemies_remain(&self, elf: bool) -> bool {
+        self.entities
+            .iter()
+            .any(|entity| matches!((entity, elf), (Goblin(_), true) | (Elf(_), false)))
+    }
+
+    fn remaining_hp(&self) -> u32 {
+        self.entities
+            .iter()
+            .filter_map(|entity| match entity {
+                Goblin(unit) | Elf(unit) => Some(u32::from(unit.hp.0)),
+                _ => None,
+            })
+            .sum()
+    }
+
+    fn find_closest_target(&self, position: Position, pathfinding: &[u16]) -> Target {
+        let elf = match &self.entities[position] {
+            Elf(_) => true,
+            Goblin(_) => false,
+            entity => panic!("invalid entity {} for finding a target", entity),
+        };
+
+        match self
+            .entities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entity)| match (entity, elf) {
+                (Elf(_), false) | (Goblin(_), true) => Some(Position(i)),
+                _ => None,
+            })
+            .flat_map(|position| {
+                DIRECTIONS.iter().map(move |direction| {
+                    if let Some((position, Empty)) = position
+                        .to(self.width, direction)
+                        .map(|position| (position, &self.entities[position]))
+                    {
+                        (position, pathfinding[position])
+                    } else {
+                        (position, UNREACHABLE)
+                    }
+                })
+            })
+            .min_by_key(|&(position, distance)| (distance, position.0))
+        {
+            Some((position, distance)) if distance != UNREACHABLE => Found(position),
+            _ => Unreachable,
+        }
+    }
+
+    /// Picks the first step from `position`, given distances measured from the target.
+    fn find_path_to_target(&self, position: Position, pathfinding: &[u16]) -> Position {
+        DIRECTIONS
+            .iter()
+            .filter_map(|direction| position.to(self.width, direction))
+            .filter(|&next| pathfinding[next] != UNREACHABLE)
+            .min_by_key(|&next| (pathfinding[next], next.0))
+            .unwrap()
+    }
+
+    fn snapshot(&self, round: u32) -> Snapshot {
+        Snapshot {
+            round,
+            board: self.to_string(),
+            units: self
+                .entities
+                .iter()
+                .enumerate()
+                .filter_map(|(i, entity)| match entity {
+                    Elf(unit) => Some((Faction::Elves, Position(i), unit.hp.0)),
+                    Goblin(unit) => Some((Faction::Goblins, Position(i), unit.hp.0)),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Resets every unit to `cfg.starting_hp`.
+    pub fn reset(&mut self, cfg: &Config) {
+        for entity in self.entities.iter_mut() {
+            if let Elf(unit) | Goblin(unit) = entity {
+                unit.hp = HitPoints(cfg.starting_hp);
+            }
+        }
+
+        self.started = true;
+    }
+
+    /// Starts a battle, resetting every unit to `cfg.starting_hp`.
+    pub fn rounds<'a>(&'a mut self, cfg: &'a Config) -> Rounds<'a> {
+        self.reset(cfg);
+
+        Rounds {
+            elves: self.count(Faction::Elves),
+            board: self,
+            cfg,
+            scratch: Scratch::default(),
+            round: 0,
+            winner: None,
+        }
+    }
+
+    /// Plays one full round. The first round played on a freshly parsed
+    /// board resets every unit to `cfg.starting_hp`.
+    pub fn round(&mut self, cfg: &Config, scratch: &mut Scratch) -> RoundResult {
+        if !self.started {
+            self.reset(cfg);
+        }
+
+        let Scratch {
+            turn_order,
+            pathfinding,
+        } = scratch;
+        pathfinding.resize(self.entities.len(), 0);
+
+        self.calculate_turn_order(turn_order);
+
+        for &(position, id) in turn_order.iter() {
+            // units only move on their own turn, so anything else here
+            // means the unit died and the square was emptied or retaken
+            let elf = match &self.entities[position] {
+                Elf(unit) if unit.id == id => true,
+                Goblin(unit) if unit.id == id => false,
+                _ => continue,
+            };
+
+            // checked before picking an action: a walled-in unit can only
+            // ever Wait, so combat would never end from inside Move
+            if !self.enemies_remain(elf) {
+                return RoundResult::Over(if elf {
+                    Faction::Elves
+                } else {
+                    Faction::Goblins
+                });
+            }
+
+            match self.pick_action(position) {
+                Wait => (),
+                Attack(target) => self.attack(target, cfg),
+                Move => {
+                    self.update_paths(position, pathfinding);
+                    match self.find_closest_target(position, pathfinding) {
+                        Found(target) => {
+                            self.update_paths(target, pathfinding);
+                            let new_position = self.find_path_to_target(position, pathfinding);
+                            self.move_to(position, new_position);
+                            if let Attack(target) = self.pick_action(new_position) {
+                                self.attack(target, cfg);
+                            }
+                        }
+                        Unreachable => (),
+                    }
+                }
+            }
+        }
+
+        RoundResult::Complete
+    }
+
+    pub fn simulate(&mut self, cfg: &Config, must_survive: bool) -> Option<Outcome> {
+        let mut rounds = self.rounds(cfg);
+
+        while rounds.next().is_some() {
+            if rounds.elf_died() && must_survive {
+                return None;
+            }
+        }
+
+        if rounds.elf_died() && must_survive {
+            return None;
+        }
+
+        let (full_rounds, winner) = (rounds.round, rounds.winner?);
+
+        Some(Outcome {
+            full_rounds,
+            remaining_hp: self.remaining_hp(),
+            winner,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Snapshot {
+    pub round: u32,
+    pub board: String,
+    pub units: Vec<(Faction, Position, u8)>,
+}
+
+/// Plays out a battle one full round at a time; `winner` is set once it ends.
+pub struct Rounds<'a> {
+    board: &'a mut Board,
+    cfg: &'a Config,
+    scratch: Scratch,
+    round: u32,
+    elves: usize,
+    winner: Option<Faction>,
+}
+
+impl<'a> Rounds<'a> {
+    fn elf_died(&self) -> bool {
+        self.board.count(Faction::Elves) < self.elves
+    }
+}
+
+impl<'a> Iterator for Rounds<'a> {
+    type Item = Snapshot;
+
+    fn next(&mut self) -> Option<Snapshot> {
+        if self.winner.is_some() {
+            return None;
+        }
+
+        match self.board.round(self.cfg, &mut self.scratch) {
+            RoundResult::Complete => {
+                self.round += 1;
+                Some(self.board.snapshot(self.round))
+            }
+            RoundResult::Over(winner) => {
+                self.winner = Some(winner);
+                None
+            }
+        }
+    }
+}
+
+/// Finds the lowest elf attack power above `cfg.elf_ap` at which no elf dies.
+pub fn min_elf_ap(board: &Board, cfg: &Config) -> Result<(Config, Outcome), Error> {
+    // an empty range when `cfg.elf_ap` is already the maximum
+    for elf_ap in (cfg.elf_ap..u8::MAX).map(|elf_ap| elf_ap + 1) {
+        let cfg = Config { elf_ap, ..*cfg };
+
+        if let Some(outcome) = board.clone().simulate(&cfg, true) {
+            return Ok((cfg, outcome));
+        }
+    }
+
+    Err("Elves exceeded maximum attack power while still suffering casualties".into())
+}
+
+pub fn solve(input: &str) -> Result<(u32, u32), Error> {
+    let board: Board = input.parse()?;
+    let cfg = Config::default();
+
+    let part1 = board
+        .clone()
+        .simulate(&cfg, false)
+        .ok_or("combat never ended")?;
+    let (_, part2) = min_elf_ap(&board, &cfg)?;
+
+    Ok((part1.score(), part2.score()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The six part-one combats: board, outcome, full rounds, remaining HP.
+    const EXAMPLES: [(&str, u32, u32, u32); 6] = [
+        (
+            "#######\n#.G...#\n#...EG#\n#.#.#G#\n#..G#E#\n#.....#\n#######\n",
+            27730,
+            47,
+            590,
+        ),
+        (
+            "#######\n#G..#E#\n#E#E.E#\n#G.##.#\n#...#E#\n#...E.#\n#######\n",
+            36334,
+            37,
+            982,
+        ),
+        (
+            "#######\n#E..EG#\n#.#G.E#\n#E.##E#\n#G..#.#\n#..E#.#\n#######\n",
+            39514,
+            46,
+            859,
+        ),
+        (
+            "#######\n#E.G#.#\n#.#G..#\n#G.#.G#\n#G..#.#\n#...E.#\n#######\n",
+            27755,
+            35,
+            793,
+        ),
+        (
+            "#######\n#.E...#\n#.#..G#\n#.###.#\n#E#G#G#\n#...#G#\n#######\n",
+            28944,
+            54,
+            536,
+        ),
+        (
+            "#########\n#G......#\n#.E.#...#\n#..##..G#\n#...##..#\n#...#...#\n#.G...G.#\n#.....G.#\n#########\n",
+            18740,
+            20,
+            937,
+        ),
+    ];
+
+    fn fight(input: &str, cfg: &Config) -> Outcome {
+        let mut board: Board = input.parse().unwrap();
+
+        board.simulate(cfg, false).unwrap()
+    }
+
+    #[test]
+    fn published_outcomes() {
+        let winners = [
+            Faction::Goblins,
+            Faction::Elves,
+            Faction::Elves,
+            Faction::Goblins,
+            Faction::Goblins,
+            Faction::Goblins,
+        ];
+
+        for (i, &(input, score, _, _)) in EXAMPLES.iter().enumerate() {
+            let outcome = fight(input, &Config::default());
+
+            assert_eq!(outcome.score(), score, "example {}", i + 1);
+            assert_eq!(outcome.winner, winners[i], "example {}", i + 1);
+        }
+    }
+
+    #[test]
+    fn full_rounds_exclude_the_final_partial_round() {
+        for (i, &(input, _, rounds, hp)) in EXAMPLES.iter().enumerate() {
+            let outcome = fight(input, &Config::default());
+
+            assert_eq!(
+                (outcome.full_rounds, outcome.remaining_hp),
+                (rounds, hp),
+                "example {}",
+                i + 1
+            );
+        }
+    }
+
+    fn position(board: &Board, x: usize, y: usize) -> Position {
+        Position(y * board.width + x)
+    }
+
+    fn first_step(board: &Board, position: Position) -> Position {
+        let mut pathfinding = vec![0; board.entities.len()];
+        board.update_paths(position, &mut pathfinding);
+
+        match board.find_closest_target(position, &pathfinding) {
+            Found(target) => {
+                board.update_paths(target, &mut pathfinding);
+                board.find_path_to_target(position, &pathfinding)
+            }
+            Unreachable => position,
+        }
+    }
+
+    #[test]
+    fn nearest_target_in_reading_order() {
+        // The "Targets" diagram: (3,1), (2,2) and (1,3) are all 2 steps away.
+        let board: Board = "#######\n#E..G.#\n#...#.#\n#.G.#G#\n#######\n"
+            .parse()
+            .unwrap();
+        let elf = position(&board, 1, 1);
+        let mut pathfinding = vec![0; board.entities.len()];
+        board.update_paths(elf, &mut pathfinding);
+
+        match board.find_closest_target(elf, &pathfinding) {
+            Found(target) => assert_eq!(target.0, position(&board, 3, 1).0),
+            Unreachable => panic!("no target found"),
+        }
+        assert_eq!(first_step(&board, elf).0, position(&board, 2, 1).0);
+    }
+
+    #[test]
+    fn first_step_in_reading_order() {
+        // Both right and down lead to (4,2) in 3 steps; right comes first.
+        let board: Board = "#######\n#.E...#\n#.....#\n#...G.#\n#######\n"
+            .parse()
+            .unwrap();
+        let elf = position(&board, 2, 1);
+
+        assert_eq!(first_step(&board, elf).0, position(&board, 3, 1).0);
+    }
+
+    #[test]
+    fn movement_example() {
+        let mut board: Board = "#########\n#G..G..G#\n#.......#\n#.......#\n#G..E..G#\n#.......#\n#.......#\n#G..G..G#\n#########\n"
+            .parse()
+            .unwrap();
+        let frames = [
+            "#########\n#.G...G.#\n#...G...#\n#...E..G#\n#.G.....#\n#.......#\n#G..G..G#\n#.......#\n#########\n",
+            "#########\n#..G.G..#\n#...G...#\n#.G.E.G.#\n#.......#\n#G..G..G#\n#.......#\n#.......#\n#########\n",
+            "#########\n#.......#\n#..GGG..#\n#..GEG..#\n#G..G...#\n#......G#\n#.......#\n#.......#\n#########\n",
+        ];
+        let cfg = Config::default();
+        let mut scratch = Scratch::default();
+
+        for (round, frame) in frames.iter().enumerate() {
+            assert_eq!(board.round(&cfg, &mut scratch), RoundResult::Complete);
+            assert_eq!(board.to_string(), *frame, "round {}", round + 1);
+        }
+
+        // Nobody moves again once everyone is in range or blocked.
+        for round in 4..10 {
+            assert_eq!(board.round(&cfg, &mut scratch), RoundResult::Complete);
+            assert_eq!(board.to_string(), frames[2], "round {}", round);
+        }
+    }
+
+    #[test]
+    fn vacated_squares_do_not_grant_extra_turns() {
+        // The goblin at (3,2) dies before its turn and the elf at (2,2) steps
+        // right into the square it left; that elf must not act a second time.
+        let cfg = Config {
+            elf_ap: 200,
+            ..Config::default()
+        };
+        let outcome = fight("#######\n#..E..#\n#.EG.G#\n#.....#\n#######\n", &cfg);
+
+        assert_eq!(
+            (outcome.full_rounds, outcome.remaining_hp, outcome.winner),
+            (1, 397, Faction::Elves)
+        );
+    }
+
+    #[test]
+    fn walled_in_unit_ends_combat() {
+        // The elf at (1,1) can never move or attack, so combat must end when
+        // its turn comes round with no goblins left.
+        let outcome = fight("#######\n#E#.EG#\n###...#\n#######\n", &Config::default());
+
+        assert_eq!(
+            (outcome.full_rounds, outcome.remaining_hp, outcome.winner),
+            (67, 202, Faction::Elves)
+        );
+    }
+
+    #[test]
+    fn paths_longer_than_255() {
+        // A 200x200 serpentine: odd rows are corridors joined by a single gap
+        // that alternates between the right and left ends of each wall row.
+        let n = 200;
+        let mut input = String::new();
+
+        for y in 0..n {
+            for x in 0..n {
+                let gap = if (y / 2) % 2 == 1 { n - 2 } else { 1 };
+
+                input.push(match (x, y) {
+                    (100, 1) => 'E',
+                    (1, 197) => 'G',
+                    _ if x == 0 || x == n - 1 || y == 0 || y == n - 1 => '#',
+                    _ if y % 2 == 1 || x == gap => '.',
+                    _ => '#',
+                });
+            }
+            input.push('\n');
+        }
+
+        let board: Board = input.parse().unwrap();
+        let elf = position(&board, 100, 1);
+        let mut pathfinding = vec![0; board.entities.len()];
+        board.update_paths(elf, &mut pathfinding);
+
+        let target = match board.find_closest_target(elf, &pathfinding) {
+            Found(target) => target,
+            Unreachable => panic!("the goblin should be reachable"),
+        };
+        assert!(pathfinding[target.0] > 255, "{}", pathfinding[target.0]);
+
+        // The only way down is the gap at the right end of row 2.
+        assert_eq!(first_step(&board, elf).0, position(&board, 101, 1).0);
+    }
+
+    #[test]
+    fn equal_hp_targets_in_reading_order() {
+        let board: Board = "#####\n#.G.#\n#GEG#\n#...#\n#####\n".parse().unwrap();
+        match board.pick_action(position(&board, 2, 2)) {
+            Attack(target) => assert_eq!(target.0, position(&board, 2, 1).0),
+            action => panic!("{:?}", action),
+        }
+
+        let board: Board = "#####\n#...#\n#GEG#\n#.G.#\n#####\n".parse().unwrap();
+        match board.pick_action(position(&board, 2, 2)) {
+            Attack(target) => assert_eq!(target.0, position(&board, 1, 2).0),
+            action => panic!("{:?}", action),
+        }
+    }
+
+    #[test]
+    fn default_config() {
+        let cfg = Config::default();
+
+        assert_eq!((cfg.elf_ap, cfg.goblin_ap, cfg.starting_hp), (3, 3, 200));
+    }
+
+    #[test]
+    fn elf_attack_power() {
+        let cfg = Config {
+            elf_ap: 15,
+            ..Config::default()
+        };
+        let mut board: Board = EXAMPLES[0].0.parse().unwrap();
+        let outcome = board.simulate(&cfg, true).unwrap();
+        assert_eq!((outcome.full_rounds, outcome.remaining_hp), (29, 172));
+        assert_eq!(outcome.score(), 4988);
+
+        let cfg = Config {
+            elf_ap: 14,
+            ..Config::default()
+        };
+        let mut board: Board = EXAMPLES[0].0.parse().unwrap();
+        assert!(board.simulate(&cfg, true).is_none());
+    }
+
+    #[test]
+    fn starting_hp_and_goblin_attack_power() {
+        let cfg = Config {
+            elf_ap: 5,
+            goblin_ap: 1,
+            starting_hp: 10,
+        };
+        let outcome = fight("#####\n#EG.#\n#####\n", &cfg);
+
+        assert_eq!(
+            (outcome.full_rounds, outcome.remaining_hp, outcome.winner),
+            (2, 9, Faction::Elves)
+        );
+    }
+
+    #[test]
+    fn replay_main_example() {
+        let mut board: Board = EXAMPLES[0].0.parse().unwrap();
+        let snapshots: Vec<Snapshot> = board.rounds(&Config::default()).collect();
+        let frames = [
+            (
+                1,
+                "#######\n#..G..#\n#...EG#\n#.#G#G#\n#...#E#\n#.....#\n#######\n",
+            ),
+            (
+                2,
+                "#######\n#...G.#\n#..GEG#\n#.#.#G#\n#...#E#\n#.....#\n#######\n",
+            ),
+            (
+                23,
+                "#######\n#...G.#\n#..G.G#\n#.#.#G#\n#...#E#\n#.....#\n#######\n",
+            ),
+            (
+                47,
+                "#######\n#G....#\n#.G...#\n#.#.#G#\n#...#.#\n#....G#\n#######\n",
+            ),
+        ];
+
+        assert_eq!(snapshots.len(), 47);
+        for &(round, frame) in frames.iter() {
+            let snapshot = &snapshots[round - 1];
+
+            assert_eq!(snapshot.round as usize, round);
+            assert_eq!(snapshot.board, frame, "round {}", round);
+        }
+
+        let hp: Vec<u8> = snapshots[46].units.iter().map(|unit| unit.2).collect();
+        assert_eq!(hp, vec![200, 131, 59, 200]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let input = EXAMPLES[0].0.replace('\n', "\r\n");
+
+        assert_eq!(fight(&input, &Config::default()).score(), 27730);
+
+        let board: Board = "#####\r\n#EG.#\r\n#####".parse().unwrap();
+        assert_eq!(board.to_string(), "#####\n#EG.#\n#####\n");
+    }
+
+    #[test]
+    fn ragged_lines_are_named() {
+        let error = "#####\n#EG.#\n####\n".parse::<Board>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid input: line 3 is 4 wide, expected 5"
+        );
+
+        let error = "#####\n#EX.#\n#####\n".parse::<Board>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid input: invalid character on line 2: X"
+        );
+
+        assert!("\n\n".parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn solve_published_examples() {
+        // The second combat has no published part-two answer.
+        let part_two = [
+            Some(4988),
+            None,
+            Some(31284),
+            Some(3478),
+            Some(6474),
+            Some(1140),
+        ];
+
+        for (i, &(input, score, _, _)) in EXAMPLES.iter().enumerate() {
+            let (one, two) = solve(input).unwrap();
+
+            assert_eq!(one, score, "example {}", i + 1);
+            if let Some(expected) = part_two[i] {
+                assert_eq!(two, expected, "example {}", i + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn round_by_round() {
+        let mut board: Board = EXAMPLES[0].0.parse().unwrap();
+        let cfg = Config::default();
+        let mut scratch = Scratch::default();
+        let mut rounds = 0;
+
+        while board.round(&cfg, &mut scratch) == RoundResult::Complete {
+            rounds += 1;
+        }
+
+        assert_eq!(rounds, 47);
+        assert_eq!(
+            board.round(&cfg, &mut scratch),
+            RoundResult::Over(Faction::Goblins)
+        );
+    }
+
+    #[test]
+    fn round_applies_starting_hp() {
+        let mut board: Board = "#####\n#EG.#\n#####\n".parse().unwrap();
+        let cfg = Config {
+            elf_ap: 5,
+            goblin_ap: 1,
+            starting_hp: 10,
+        };
+        let mut scratch = Scratch::default();
+
+        // At the default 200 HP the goblin would survive the first round.
+        assert_eq!(board.round(&cfg, &mut scratch), RoundResult::Complete);
+        assert_eq!(board.snapshot(1).units[1].2, 5);
+        assert_eq!(board.round(&cfg, &mut scratch), RoundResult::Complete);
+        assert_eq!(
+            board.round(&cfg, &mut scratch),
+            RoundResult::Over(Faction::Elves)
+        );
+        assert_eq!(board.remaining_hp(), 9);
+
+        // An explicit reset starts the next battle from full health again.
+        let mut board: Board = "#####\n#EG.#\n#####\n".parse().unwrap();
+        board.reset(&Config::default());
+        board.round(&cfg, &mut scratch);
+        assert_eq!(board.snapshot(1).units[1].2, 195);
+    }
+
+    #[test]
+    fn scratch_is_shared_between_boards() {
+        let mut scratch = Scratch::default();
+        let cfg = Config::default();
+
+        for &(input, _, rounds, hp) in EXAMPLES.iter() {
+            let mut board: Board = input.parse().unwrap();
+            let mut full_rounds = 0;
+
+            while board.round(&cfg, &mut scratch) == RoundResult::Complete {
+                full_rounds += 1;
+            }
+
+            assert_eq!((full_rounds, board.remaining_hp()), (rounds, hp));
+        }
+    }
+
+    #[test]
+    fn min_elf_ap_at_maximum() {
+        let board: Board = EXAMPLES[0].0.parse().unwrap();
+        let cfg = Config {
+            elf_ap: u8::MAX,
+            ..Config::default()
+        };
+
+        assert!(min_elf_ap(&board, &cfg).is_err());
+
+        let cfg = Config {
+            elf_ap: u8::MAX - 1,
+            ..Config::default()
+        };
+        let (cfg, _) = min_elf_ap(&board, &cfg).unwrap();
+        assert_eq!(cfg.elf_ap, u8::MAX);
+    }
+}
@@ -0,0 +1,781 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{BufRead, Error as IoError};
+use std::ops::Index;
+use std::str::FromStr;
+
+pub use elfcode::{INSTRUCTION_COUNT, MNEMONICS};
+
+pub const THRESHOLD: usize = 3;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Invalid(String),
+    Ambiguous,
+    Usage(String),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(error: &str) -> Self {
+        Error::Invalid(error.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(error: String) -> Self {
+        Error::Invalid(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(s) => write!(f, "invalid input: {}", s),
+            Error::Ambiguous => write!(f, "samples allow more than one opcode mapping"),
+            Error::Usage(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Device(pub Vec<u64>);
+
+impl FromStr for Device {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let list = ["Before:", "After:"]
+            .iter()
+            .find_map(|label| s.trim().strip_prefix(label))
+            .and_then(|s| s.trim().strip_prefix('['))
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or("unrecognized device signature")?;
+
+        let registers = list
+            .split(',')
+            .map(|x| {
+                x.trim()
+                    .parse()
+                    .map_err(|_| Error::from(format!("invalid register value: {:?}", x.trim())))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Device(registers))
+    }
+}
+
+impl Index<u32> for Device {
+    type Output = u64;
+
+    fn index(&self, idx: u32) -> &Self::Output {
+        &self.0[idx as usize]
+    }
+}
+
+#[derive(Debug)]
+pub struct Operation {
+    pub opcode: usize,
+    pub a: u32,
+    pub b: u32,
+    pub c: u32,
+}
+
+impl FromStr for Operation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                x.parse()
+                    .map_err(|_| Error::from(format!("invalid operand: {:?}", x)))
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        match fields[..] {
+            [opcode, a, b, c] => Ok(Operation {
+                opcode: opcode as usize,
+                a,
+                b,
+                c,
+            }),
+            _ => Err(format!("expected 4 fields in operation, found {}", fields.len()).into()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Data {
+    pub before: Device,
+    pub after: Device,
+    pub operation: Operation,
+}
+
+pub type Input = (Vec<Data>, Vec<Operation>);
+
+/// Blank lines are ignored; the samples end at the first line that doesn't
+/// start with `Before:`, and everything after that is the program.
+pub fn parse<R: BufRead>(reader: R) -> Result<Input, Error> {
+    let lines = reader.lines().collect::<Result<Vec<_>, _>>()?;
+    let mut lines = lines
+        .iter()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+
+    let mut data = Vec::new();
+
+    while let Some(line) = lines.next_if(|line| line.starts_with("Before:")) {
+        let before: Device = line.parse()?;
+        let operation = lines.next().ok_or("unexpected EOF")?.parse()?;
+        let after: Device = lines.next().ok_or("unexpected EOF")?.parse()?;
+
+        if after.0.len() != before.0.len() {
+            return Err(format!(
+                "sample has {} registers before and {} after",
+                before.0.len(),
+                after.0.len()
+            )
+            .into());
+        }
+
+        data.push(Data {
+            before,
+            after,
+            operation,
+        });
+    }
+
+    let operations = lines.map(str::parse).collect::<Result<Vec<_>, _>>()?;
+
+    if operations.is_empty() {
+        return Err("missing program section".into());
+    }
+
+    Ok((data, operations))
+}
+
+/// How many samples matched exactly `k` instructions, for each `k`.
+pub type Histogram = [usize; INSTRUCTION_COUNT + 1];
+
+/// Also counts the samples that match at least `threshold` instructions.
+pub fn build_sets(data: &[Data], threshold: usize) -> (Vec<HashSet<usize>>, Histogram, usize) {
+    let mut sets = vec![HashSet::new(); INSTRUCTION_COUNT];
+    let mut histogram = [0; INSTRUCTION_COUNT + 1];
+    // reset in place for every candidate, so the registers are allocated once
+    let mut device = Device(Vec::new());
+
+    for data in data {
+        let registers = data.before.0.len();
+        let op = &data.operation;
+        let fits = |value: u32, register: bool| !register || (value as usize) < registers;
+
+        let matches = (0..INSTRUCTION_COUNT)
+            .filter_map(|i| {
+                // a candidate that would read or write past the registers
+                // can't have produced the sample
+                let (a_register, b_register) = elfcode::REGISTER_OPERANDS[i];
+
+                if !(fits(op.a, a_register) && fits(op.b, b_register) && fits(op.c, true)) {
+                    return None;
+                }
+
+                device.0.clone_from(&data.before.0);
+
+                elfcode::execute(i, &mut device.0, op.a.into(), op.b.into(), op.c.into());
+
+                if device == data.after {
+                    sets[i].insert(data.operation.opcode);
+                    Some(())
+                } else {
+                    None
+                }
+            })
+            .count();
+
+        histogram[matches] += 1;
+    }
+
+    let count = histogram.iter().skip(threshold).sum();
+
+    (sets, histogram, count)
+}
+
+/// Candidate opcodes per instruction, one bit each.
+type Candidates = [u16; INSTRUCTION_COUNT];
+
+/// Narrows candidates with naked singles (an instruction with one opcode left)
+/// and hidden singles (an opcode only one instruction can still take).
+/// Returns false on a contradiction.
+fn propagate(candidates: &mut Candidates) -> bool {
+    loop {
+        let mut changed = false;
+
+        for i in 0..INSTRUCTION_COUNT {
+            let set = candidates[i];
+
+            match set.count_ones() {
+                0 => return false,
+                1 => {
+                    for (j, other) in candidates.iter_mut().enumerate() {
+                        if j != i && *other & set != 0 {
+                            *other &= !set;
+                            changed = true;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        for opcode in 0..INSTRUCTION_COUNT {
+            let bit = 1 << opcode;
+            let mut holders = candidates.iter_mut().filter(|set| **set & bit != 0);
+
+            match (holders.next(), holders.next()) {
+                (None, _) => return false,
+                (Some(set), None) if *set != bit => {
+                    *set = bit;
+                    changed = true;
+                }
+                _ => (),
+            }
+        }
+
+        if !changed {
+            return true;
+        }
+    }
+}
+
+/// Collects complete assignments into `solutions`, stopping once there are two.
+/// A uniquely solvable matrix always has a naked single (it can be permuted to
+/// triangular form), so branching only ever separates ambiguous samples from
+/// contradictory ones.
+fn search(mut candidates: Candidates, solutions: &mut Vec<Candidates>) {
+    if !propagate(&mut candidates) {
+        return;
+    }
+
+    let branch = (0..INSTRUCTION_COUNT)
+        .filter(|&i| candidates[i].count_ones() > 1)
+        .min_by_key(|&i| candidates[i].count_ones());
+
+    let i = match branch {
+        Some(i) => i,
+        None => return solutions.push(candidates),
+    };
+
+    for opcode in 0..INSTRUCTION_COUNT {
+        if candidates[i] & 1 << opcode != 0 && solutions.len() < 2 {
+            let mut guess = candidates;
+            guess[i] = 1 << opcode;
+            search(guess, solutions);
+        }
+    }
+}
+
+/// Each opcode's index into the `elfcode` instruction table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Mapping {
+    pub transform: [usize; INSTRUCTION_COUNT],
+}
+
+impl Mapping {
+    pub fn resolve(&self, opcode: usize) -> Option<usize> {
+        self.transform.get(opcode).copied()
+    }
+
+    pub fn mnemonic(&self, opcode: usize) -> Option<&'static str> {
+        self.resolve(opcode).map(|i| MNEMONICS[i])
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (opcode, &i) in self.transform.iter().enumerate() {
+            writeln!(f, "opcode {} -> {}", opcode, MNEMONICS[i])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves each opcode to an index into the `elfcode` instruction table.
+pub fn map_instructions(sets: &[HashSet<usize>]) -> Result<Mapping, Error> {
+    let mut candidates = [0; INSTRUCTION_COUNT];
+
+    for (set, candidate) in sets.iter().zip(candidates.iter_mut()) {
+        for &opcode in set {
+            if opcode >= INSTRUCTION_COUNT {
+                return Err(format!("opcode {} out of range", opcode).into());
+            }
+
+            *candidate |= 1 << opcode;
+        }
+    }
+
+    let mut solutions = Vec::new();
+    search(candidates, &mut solutions);
+
+    let solution = match solutions[..] {
+        [solution] => solution,
+        [] => return Err("unsolveable data".into()),
+        _ => return Err(Error::Ambiguous),
+    };
+
+    let mut transform = [0; INSTRUCTION_COUNT];
+
+    for (i, set) in solution.iter().enumerate() {
+        transform[set.trailing_zeros() as usize] = i;
+    }
+
+    Ok(Mapping { transform })
+}
+
+pub fn disassemble(operations: &[Operation], mapping: &Mapping) -> String {
+    operations
+        .iter()
+        .map(|op| {
+            let mnemonic = mapping.mnemonic(op.opcode).unwrap_or("????");
+            format!("{} {} {} {}\n", mnemonic, op.a, op.b, op.c)
+        })
+        .collect()
+}
+
+/// Checks every operation against the mapping and register count before
+/// running any of them.
+pub fn execute_procedure(
+    mapping: &Mapping,
+    operations: &[Operation],
+    registers: usize,
+) -> Result<Device, Error> {
+    let mut program = Vec::with_capacity(operations.len());
+
+    for (line, op) in operations.iter().enumerate() {
+        let i = mapping
+            .resolve(op.opcode)
+            .ok_or_else(|| format!("operation {}: unknown opcode {}", line, op.opcode))?;
+        let (a_register, b_register) = elfcode::REGISTER_OPERANDS[i];
+
+        for &(field, value, register) in &[
+            ("a", op.a, a_register),
+            ("b", op.b, b_register),
+            ("c", op.c, true),
+        ] {
+            if register && value as usize >= registers {
+                return Err(format!(
+                    "operation {}: {} {} {} {}: register {} = {} out of range for {} registers",
+                    line, MNEMONICS[i], op.a, op.b, op.c, field, value, registers
+                )
+                .into());
+            }
+        }
+
+        program.push(i);
+    }
+
+    let mut device = Device(vec![0; registers]);
+
+    for (&i, op) in program.iter().zip(operations) {
+        elfcode::execute(i, &mut device.0, op.a.into(), op.b.into(), op.c.into());
+    }
+
+    Ok(device)
+}
+
+/// The program runs on a device shaped like the ones in the samples.
+pub fn program_registers(data: &[Data]) -> usize {
+    data.first().map_or(4, |data| data.before.0.len())
+}
+
+/// Returns the part one count, register 0 after running the program, and
+/// the opcode mapping it was run with.
+pub fn solve(input: &str) -> Result<(usize, u64, Mapping), Error> {
+    let (data, operations) = parse(input.as_bytes())?;
+    let (sets, _, count) = build_sets(&data, THRESHOLD);
+    let mapping = map_instructions(&sets)?;
+    let device = execute_procedure(&mapping, &operations, program_registers(&data))?;
+
+    Ok((count, device[0], mapping))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(sample: &str) -> Vec<usize> {
+        let (data, _) = parse(format!("{}\n\n0 0 0 0\n", sample).as_bytes()).unwrap();
+        let (sets, _, _) = build_sets(&data, THRESHOLD);
+
+        (0..INSTRUCTION_COUNT)
+            .filter(|&i| sets[i].contains(&data[0].operation.opcode))
+            .collect()
+    }
+
+    #[test]
+    fn wide_operands() {
+        let op: Operation = "12 10 255 3".parse().unwrap();
+        assert_eq!((op.opcode, op.a, op.b, op.c), (12, 10, 255, 3));
+
+        // addi 0 12 2 and bori 0 12 2 both give 15
+        let sample = "Before: [3, 2, 1, 1]\n5 0 12 2\nAfter:  [3, 2, 15, 1]";
+        assert_eq!(matching(sample), vec![1, 7]);
+    }
+
+    #[test]
+    fn out_of_range_registers_never_match() {
+        // a register-reading candidate would index register 12
+        let sample = "Before: [12, 2, 1, 1]\n5 12 0 2\nAfter:  [12, 2, 12, 1]";
+        assert_eq!(matching(sample), vec![9]);
+
+        let sample = "Before: [3, 2, 1, 1]\n5 0 12 7\nAfter:  [3, 2, 15, 1]";
+        assert_eq!(matching(sample), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn six_registers() {
+        let input = "Before: [1, 2, 3, 4, 5, 6]\n9 4 5 5\nAfter:  [1, 2, 3, 4, 5, 11]\n\n9 0 0 0\n";
+        let (data, operations) = parse(input.as_bytes()).unwrap();
+
+        assert_eq!(data[0].before.0, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(program_registers(&data), 6);
+        assert_eq!(operations.len(), 1);
+        assert_eq!(matching(input.split("\n\n").next().unwrap()), vec![0]);
+    }
+
+    #[test]
+    fn mismatched_register_counts() {
+        let input = "Before: [1, 2, 3, 4, 5, 6]\n9 4 5 5\nAfter:  [1, 2, 3, 4]\n\n9 0 0 0\n";
+        assert!(parse(input.as_bytes()).is_err());
+    }
+
+    fn sets(candidates: &[Vec<usize>]) -> Vec<HashSet<usize>> {
+        candidates
+            .iter()
+            .map(|set| set.iter().copied().collect())
+            .collect()
+    }
+
+    const IDENTITY: Mapping = Mapping {
+        transform: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    };
+
+    #[test]
+    fn shuffled_triangle() {
+        // instruction perm[k] can be any of opcodes k..16, so the mapping is
+        // unique but only one instruction starts out with a single candidate
+        let perm = [7, 3, 12, 0, 15, 9, 1, 14, 5, 10, 2, 8, 13, 6, 11, 4];
+        let mut candidates = vec![Vec::new(); INSTRUCTION_COUNT];
+
+        for (k, &i) in perm.iter().enumerate() {
+            candidates[i] = (k..INSTRUCTION_COUNT).collect();
+        }
+
+        assert_eq!(
+            map_instructions(&sets(&candidates)).unwrap().transform,
+            perm
+        );
+    }
+
+    #[test]
+    fn hidden_singles_in_a_triangle() {
+        let candidates: Vec<Vec<usize>> = (0..16).map(|i| (i..16).collect()).collect();
+
+        assert_eq!(map_instructions(&sets(&candidates)).unwrap(), IDENTITY);
+    }
+
+    #[test]
+    fn cycle_is_ambiguous() {
+        let candidates: Vec<Vec<usize>> = (0..16).map(|i| vec![i, (i + 1) % 16]).collect();
+
+        assert!(matches!(
+            map_instructions(&sets(&candidates)),
+            Err(Error::Ambiguous)
+        ));
+    }
+
+    #[test]
+    fn swappable_pair_is_ambiguous() {
+        let mut candidates: Vec<Vec<usize>> = (0..16).map(|i| vec![i]).collect();
+        candidates[0] = vec![0, 1];
+        candidates[1] = vec![0, 1];
+
+        assert!(matches!(
+            map_instructions(&sets(&candidates)),
+            Err(Error::Ambiguous)
+        ));
+    }
+
+    #[test]
+    fn contradictions_are_unsolvable() {
+        let mut candidates: Vec<Vec<usize>> = (0..16).map(|i| vec![i]).collect();
+        candidates[3] = vec![4];
+
+        match map_instructions(&sets(&candidates)) {
+            Err(Error::Invalid(s)) => assert_eq!(s, "unsolveable data"),
+            other => panic!("{:?}", other),
+        }
+
+        candidates[3] = vec![3, 16];
+        assert!(map_instructions(&sets(&candidates)).is_err());
+    }
+
+    fn operations(lines: &[&str]) -> Vec<Operation> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn disassemble_three_instructions() {
+        // opcode k resolves to instruction 15 - k
+        let mut mapping = IDENTITY;
+        mapping.transform.reverse();
+
+        assert_eq!(
+            disassemble(&operations(&["3 3 2 1", "12 1 10 0", "0 0 0 2"]), &mapping),
+            "gtrr 3 2 1\nmuli 1 10 0\neqrr 0 0 2\n"
+        );
+    }
+
+    #[test]
+    fn disassemble_unknown_opcode() {
+        assert_eq!(
+            disassemble(&operations(&["16 1 2 3"]), &IDENTITY),
+            "???? 1 2 3\n"
+        );
+    }
+
+    const SAMPLE: &str = "Before: [3, 2, 1, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]\n";
+
+    #[test]
+    fn worked_sample_histogram() {
+        let (data, _) = parse(format!("{}\n\n9 0 0 0\n", SAMPLE).as_bytes()).unwrap();
+        let mut expected = [0; INSTRUCTION_COUNT + 1];
+        expected[3] = 1;
+
+        let (_, histogram, count) = build_sets(&data, 3);
+        assert_eq!(histogram, expected);
+        assert_eq!(count, 1);
+
+        let (_, histogram, count) = build_sets(&data, 4);
+        assert_eq!(histogram, expected);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn blank_lines_and_crlf() {
+        let input = format!("{}\n\n\n{}\n\n\n\n\n1 2 3 0\n4 5 6 1\n\n", SAMPLE, SAMPLE)
+            .replace('\n', "\r\n");
+        let (data, operations) = parse(input.as_bytes()).unwrap();
+
+        assert_eq!((data.len(), operations.len()), (2, 2));
+        assert_eq!(operations[1].c, 1);
+
+        let input = format!("{}{}\n1 2 3 0", SAMPLE, SAMPLE);
+        let (data, operations) = parse(input.as_bytes()).unwrap();
+
+        assert_eq!((data.len(), operations.len()), (2, 1));
+    }
+
+    #[test]
+    fn missing_program() {
+        let input = format!("{}\n{}\n\n\n", SAMPLE, SAMPLE);
+        assert_eq!(
+            parse(input.as_bytes()).unwrap_err().to_string(),
+            "invalid input: missing program section"
+        );
+
+        let input = "Before: [3, 2, 1, 1]\n9 2 1 2\n";
+        assert_eq!(
+            parse(input.as_bytes()).unwrap_err().to_string(),
+            "invalid input: unexpected EOF"
+        );
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let error =
+            execute_procedure(&IDENTITY, &operations(&["9 5 0 0", "99 0 0 0"]), 4).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid input: operation 1: unknown opcode 99"
+        );
+    }
+
+    #[test]
+    fn register_out_of_range() {
+        // seti takes `a` as an immediate, so 7 is fine there
+        let device = execute_procedure(&IDENTITY, &operations(&["9 7 0 0"]), 4).unwrap();
+        assert_eq!(device.0, vec![7, 0, 0, 0]);
+
+        let mut mapping = IDENTITY;
+        mapping.transform.swap(8, 9);
+        let error = execute_procedure(&mapping, &operations(&["9 7 0 0"]), 4).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid input: operation 0: setr 7 0 0: register a = 7 out of range for 4 registers"
+        );
+
+        let error = execute_procedure(&IDENTITY, &operations(&["9 1 0 4"]), 4).unwrap_err();
+        assert!(error.to_string().contains("register c = 4"), "{}", error);
+
+        assert!(execute_procedure(&IDENTITY, &operations(&["0 5 5 5"]), 6).is_ok());
+    }
+
+    #[test]
+    fn worked_sample_matches_three() {
+        assert_eq!(
+            matching(SAMPLE)
+                .into_iter()
+                .map(|i| MNEMONICS[i])
+                .collect::<Vec<_>>(),
+            vec!["addi", "mulr", "seti"]
+        );
+    }
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Clones the registers for every candidate, as `build_sets` used to.
+    fn naive_sets(data: &[Data]) -> Vec<HashSet<usize>> {
+        let mut sets = vec![HashSet::new(); INSTRUCTION_COUNT];
+
+        for data in data {
+            let op = &data.operation;
+
+            for (i, set) in sets.iter_mut().enumerate() {
+                let mut device = data.before.clone();
+                elfcode::execute(i, &mut device.0, op.a.into(), op.b.into(), op.c.into());
+
+                if device == data.after {
+                    set.insert(op.opcode);
+                }
+            }
+        }
+
+        sets
+    }
+
+    #[test]
+    fn scratch_device_matches_naive() {
+        let mut state = 12345;
+        let mut random = |n: u64| xorshift(&mut state) % n;
+
+        let data: Vec<Data> = (0..100_000)
+            .map(|_| {
+                let before = Device((0..4).map(|_| random(4)).collect());
+                let operation = Operation {
+                    opcode: random(16) as usize,
+                    a: random(4) as u32,
+                    b: random(4) as u32,
+                    c: random(4) as u32,
+                };
+                let mut after = before.clone();
+                elfcode::execute(
+                    random(16) as usize,
+                    &mut after.0,
+                    operation.a.into(),
+                    operation.b.into(),
+                    operation.c.into(),
+                );
+
+                Data {
+                    before,
+                    after,
+                    operation,
+                }
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        let (sets, histogram, _) = build_sets(&data, THRESHOLD);
+        let elapsed = start.elapsed();
+
+        assert!(elapsed.as_secs() < 2, "{:?}", elapsed);
+        assert_eq!(histogram.iter().sum::<usize>(), data.len());
+        assert_eq!(sets, naive_sets(&data));
+    }
+
+    fn apply(i: usize, registers: &[u64], a: u32, b: u32, c: u32) -> Device {
+        let mut device = Device(registers.to_vec());
+        elfcode::execute(i, &mut device.0, a.into(), b.into(), c.into());
+        device
+    }
+
+    #[test]
+    fn determined_mapping_table() {
+        // opcode k is instruction (5k + 3) % 16
+        let perm: Vec<usize> = (0..16).map(|k| (k * 5 + 3) % 16).collect();
+        let registers = [[11, 22, 37, 45], [3, 1, 2, 0], [2, 2, 9, 1], [0, 3, 0, 2]];
+        let mut data = Vec::new();
+
+        for (opcode, &i) in perm.iter().enumerate() {
+            // the sample that the fewest other instructions explain
+            let mut best: Option<(usize, Data)> = None;
+
+            for before in registers.iter() {
+                for (a, b, c) in (0..64).map(|x| (x / 16, x / 4 % 4, x % 4)) {
+                    let after = apply(i, before, a, b, c);
+                    let matches = (0..16)
+                        .filter(|&j| apply(j, before, a, b, c) == after)
+                        .count();
+
+                    if best.as_ref().is_none_or(|&(fewest, _)| matches < fewest) {
+                        let operation = Operation { opcode, a, b, c };
+                        let before = Device(before.to_vec());
+                        best = Some((
+                            matches,
+                            Data {
+                                before,
+                                after,
+                                operation,
+                            },
+                        ));
+                    }
+                }
+            }
+
+            data.push(best.unwrap().1);
+        }
+
+        let (sets, _, _) = build_sets(&data, THRESHOLD);
+        let mapping = map_instructions(&sets).unwrap();
+        assert_eq!(mapping.transform.to_vec(), perm);
+
+        let table = mapping.to_string();
+        assert_eq!(table.lines().count(), 16);
+        assert!(
+            table.starts_with("opcode 0 -> muli\nopcode 1 -> setr\nopcode 2 -> eqir\n"),
+            "{}",
+            table
+        );
+        assert_eq!(mapping.mnemonic(15), Some("eqri"));
+        assert_eq!(mapping.mnemonic(16), None);
+    }
+
+    #[test]
+    fn solve_returns_the_mapping() {
+        let input = std::fs::read_to_string("../inputs/input-16-01.txt").unwrap();
+        let (count, register, mapping) = solve(&input).unwrap();
+
+        assert_eq!((count, register), (607, 577));
+
+        let (data, operations) = parse(input.as_bytes()).unwrap();
+        let device = execute_procedure(&mapping, &operations, program_registers(&data)).unwrap();
+        assert_eq!(device[0], register);
+    }
+}
@@ -0,0 +1,1068 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{Error as IoError, Write};
+use std::num::ParseIntError;
+use std::ops::Range;
+use std::str::FromStr;
+
+const SPRING: usize = 500;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    ParseInt(ParseIntError),
+    Invalid(String),
+    Usage(String),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(error: ParseIntError) -> Self {
+        Error::ParseInt(error)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(error: &str) -> Self {
+        Error::Invalid(error.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(error: String) -> Self {
+        Error::Invalid(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::ParseInt(e) => fmt::Display::fmt(e, f),
+            Error::Invalid(s) => write!(f, "invalid input: {}", s),
+            Error::Usage(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+pub enum Blueprints {
+    Horizontal { x: Range<usize>, y: usize },
+    Vertical { x: usize, y: Range<usize> },
+}
+
+fn parse_num(s: &str, range: Range<usize>) -> Result<usize, Error> {
+    match s.get(range) {
+        Some(s) => s.parse().map_err(Error::from),
+        _ => Err(s.into()),
+    }
+}
+
+impl FromStr for Blueprints {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let horizontal = match s.get(0..2) {
+            Some("x=") => false,
+            Some("y=") => true,
+            _ => return Err(s.into()),
+        };
+
+        let (comma, dot) = match (s.find(','), s.find('.')) {
+            (Some(c), Some(d)) if d > c => (c, d),
+            _ => return Err(s.into()),
+        };
+
+        match (s.get(comma..comma + 4), s.get(dot..dot + 2), horizontal) {
+            (Some(", x="), Some(".."), true) | (Some(", y="), Some(".."), false) => (),
+            _ => return Err(s.into()),
+        }
+
+        let num = parse_num(s, 2..comma)?;
+        let start = parse_num(s, comma + 4..dot)?;
+        let end = parse_num(s, dot + 2..s.len())?;
+
+        if end < start {
+            return Err(format!("range {}..{} runs backwards: {}", start, end, s).into());
+        }
+
+        let range = start..end + 1;
+
+        Ok(if horizontal {
+            Blueprints::Horizontal { x: range, y: num }
+        } else {
+            Blueprints::Vertical { x: num, y: range }
+        })
+    }
+}
+
+fn find_extremes(blueprints: &[Blueprints]) -> (usize, usize, usize, usize) {
+    blueprints.iter().fold(
+        (usize::MAX, usize::MAX, usize::MIN, usize::MIN),
+        |(x_ming, y_ming, x_maxg, y_maxg), blueprint| {
+            let (x_min, y_min, x_max, y_max) = match blueprint {
+                Blueprints::Vertical {
+                    x,
+                    y: Range { start, end },
+                } => (*x, *start, *x, *end - 1),
+                Blueprints::Horizontal {
+                    x: Range { start, end },
+                    y,
+                } => (*start, *y, *end - 1, *y),
+            };
+
+            (
+                if x_min < x_ming { x_min } else { x_ming },
+                if y_min < y_ming { y_min } else { y_ming },
+                if x_max > x_maxg { x_max } else { x_maxg },
+                if y_max > y_maxg { y_max } else { y_maxg },
+            )
+        },
+    )
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Object {
+    Sand,
+    Visited,
+    Clay,
+    Water,
+}
+
+impl Object {
+    fn is_wet(self) -> bool {
+        self == Object::Visited || self == Object::Water
+    }
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            Object::Sand => [210, 180, 140],
+            Object::Visited => [150, 200, 255],
+            Object::Clay => [120, 70, 30],
+            Object::Water => [30, 70, 220],
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Sand => write!(f, "."),
+            Object::Visited => write!(f, "|"),
+            Object::Clay => write!(f, "#"),
+            Object::Water => write!(f, "~"),
+        }
+    }
+}
+
+enum Job {
+    Fall {
+        x: usize,
+        y: usize,
+    },
+    Spread {
+        x: usize,
+        y: usize,
+    },
+    SpreadRight {
+        x: usize,
+        y: usize,
+        left_end: usize,
+        left_object: Object,
+    },
+}
+
+pub struct Map {
+    objects: Vec<Object>,
+    depth: usize,
+    width: usize,
+    y_min: usize,
+    y_max: usize,
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows = self.objects.chunks_exact(self.width);
+
+        for &object in rows.next().unwrap() {
+            write!(f, "{}", object)?;
+        }
+
+        for row in rows {
+            writeln!(f)?;
+
+            for &object in row {
+                write!(f, "{}", object)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Map {
+    // The grid runs from the spring's row down to y_max, but only rows in the
+    // clay's y_min..=y_max band are scored.
+    pub fn new(width: usize, y_min: usize, y_max: usize) -> Self {
+        let depth = y_max + 1;
+
+        Map {
+            objects: vec![Object::Sand; depth * width],
+            depth,
+            width,
+            y_min,
+            y_max,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    fn get(&self, x: usize, y: usize) -> Object {
+        self.objects[self.index(x, y)]
+    }
+
+    fn set(&mut self, x: usize, y: usize, object: Object) {
+        let index = self.index(x, y);
+        self.objects[index] = object;
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [Object] {
+        &mut self.objects[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn write_ppm<W: Write>(&self, mut out: W) -> Result<(), IoError> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.depth)?;
+
+        for &object in &self.objects {
+            out.write_all(&object.color())?;
+        }
+
+        out.flush()
+    }
+
+    pub fn fill(&mut self, x: usize, y: usize) {
+        let mut jobs = vec![Job::Fall { x, y }];
+
+        while let Some(job) = jobs.pop() {
+            self.run(job, &mut jobs);
+        }
+    }
+
+    fn run(&mut self, job: Job, jobs: &mut Vec<Job>) {
+        match job {
+            Job::Fall { x, y } => self.fall_down(x, y, jobs),
+            Job::Spread { x, y } => self.spread_left(x, y, jobs),
+            Job::SpreadRight {
+                x,
+                y,
+                left_end,
+                left_object,
+            } => self.spread_right(x, y, left_end, left_object, jobs),
+        }
+    }
+
+    /// Places clay at grid coordinates (x, y); call `resimulate_from` with the
+    /// same position to bring the water up to date.
+    pub fn add_clay(&mut self, x: usize, y: usize) {
+        self.set(x, y, Object::Clay);
+        self.y_min = self.y_min.min(y);
+    }
+
+    pub fn resimulate_from(&mut self, (x, y): (usize, usize)) {
+        // Clear everything wet below the new clay, raising the top while it
+        // would cut a settled pool in half. The spring's row is never cleared.
+        let mut top = y.max(1);
+        let basin = loop {
+            let basin = self.basin(x, y, top);
+            let cut = top > 1
+                && basin.iter().any(|&i| {
+                    i / self.width == top
+                        && self.objects[i] == Object::Water
+                        && self.objects[i - self.width] == Object::Water
+                });
+
+            if !cut {
+                break basin;
+            }
+
+            top -= 1;
+        };
+
+        for &i in &basin {
+            self.objects[i] = Object::Sand;
+        }
+
+        // Anything still wet right above the cleared tiles or the new clay keeps
+        // feeding them: restart those streams so they land on whatever is there now.
+        let mut feeders: Vec<_> = basin.iter().map(|&i| i - self.width).collect();
+
+        if y > 0 {
+            feeders.push(self.index(x, y - 1));
+        }
+
+        let mut jobs = Vec::new();
+
+        for i in feeders {
+            let (x, y) = (i % self.width, i / self.width);
+
+            match self.objects[i] {
+                Object::Visited => {
+                    self.objects[i] = Object::Sand;
+                    jobs.push(Job::Fall { x, y });
+                }
+                Object::Water if self.objects[i + self.width] == Object::Sand => {
+                    jobs.push(Job::Fall { x, y: y + 1 });
+                }
+                _ => (),
+            }
+        }
+
+        while let Some(job) = jobs.pop() {
+            self.run(job, &mut jobs);
+        }
+    }
+
+    // Wet tiles connected to (x, y) that lie no higher than row `top`.
+    fn basin(&self, x: usize, y: usize, top: usize) -> HashSet<usize> {
+        let mut basin = HashSet::new();
+        let mut stack = vec![self.index(x, y)];
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % self.width, i / self.width);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < self.width).then(|| i + 1),
+                (y > top).then(|| i - self.width),
+                (y + 1 < self.depth).then(|| i + self.width),
+            ];
+
+            for next in neighbours.iter().flatten().cloned() {
+                if self.objects[next].is_wet() && basin.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        basin
+    }
+
+    fn fall_down(&mut self, x: usize, y: usize, jobs: &mut Vec<Job>) {
+        if self.get(x, y) != Object::Sand {
+            return;
+        }
+
+        let start = self.index(x, y);
+        let end = self.objects[start..]
+            .iter()
+            .step_by(self.width)
+            .position(|&object| object == Object::Clay || object == Object::Water)
+            .map(|i| y + i);
+
+        for object in self.objects[start..]
+            .iter_mut()
+            .step_by(self.width)
+            .take(end.unwrap_or(self.depth) - y)
+        {
+            *object = Object::Visited;
+        }
+
+        if let Some(end) = end {
+            jobs.push(Job::Spread { x, y: end - 1 });
+        }
+    }
+
+    // Both scans walk outward from x and stop at the first wall or drop, so
+    // each spread only touches its own basin rather than the whole row.
+    fn spread_left(&mut self, x: usize, y: usize, jobs: &mut Vec<Job>) {
+        let (row, below) = self.objects[y * self.width..(y + 2) * self.width].split_at(self.width);
+
+        let (left_end, left_object) = row[..x]
+            .iter()
+            .cloned()
+            .zip(below[..x].iter().cloned())
+            .enumerate()
+            .rev()
+            .find_map(|(i, (object, below))| match (object, below) {
+                (Object::Clay, _)
+                | (Object::Sand, Object::Sand)
+                | (Object::Sand, Object::Visited)
+                | (Object::Visited, Object::Sand)
+                | (Object::Visited, Object::Visited) => Some((i + 1, object)),
+                _ => None,
+            })
+            // Nothing stops the water before the border, so it runs off the map.
+            .unwrap_or((0, Object::Visited));
+
+        // The right-hand scan has to see whatever the left overflow leaves behind.
+        jobs.push(Job::SpreadRight {
+            x,
+            y,
+            left_end,
+            left_object,
+        });
+
+        for object in self.row_mut(y)[left_end..x].iter_mut() {
+            *object = Object::Visited;
+        }
+
+        if left_object == Object::Sand {
+            jobs.push(Job::Fall { x: left_end - 1, y });
+        }
+    }
+
+    fn spread_right(
+        &mut self,
+        x: usize,
+        y: usize,
+        left_end: usize,
+        left_object: Object,
+        jobs: &mut Vec<Job>,
+    ) {
+        let (row, below) = self.objects[y * self.width..(y + 2) * self.width].split_at(self.width);
+
+        let (right_end, right_object) = row[x..]
+            .iter()
+            .cloned()
+            .zip(below[x..].iter().cloned())
+            .enumerate()
+            .find_map(|(i, (object, below))| match (object, below) {
+                (Object::Clay, _) | (Object::Water, _) => Some((x + i, Object::Clay)),
+                (Object::Sand, Object::Sand)
+                | (Object::Sand, Object::Visited)
+                | (Object::Visited, Object::Sand)
+                | (Object::Visited, Object::Visited) => Some((x + i, object)),
+                _ => None,
+            })
+            .unwrap_or((self.width, Object::Visited));
+
+        for object in self.row_mut(y)[x..right_end].iter_mut() {
+            *object = Object::Visited;
+        }
+
+        if right_object == Object::Sand {
+            jobs.push(Job::Fall { x: right_end, y });
+        }
+
+        if let (Object::Clay, Object::Clay) = (left_object, right_object) {
+            for object in self.row_mut(y)[left_end..right_end].iter_mut() {
+                *object = Object::Water;
+            }
+
+            // Rise from wherever this row is fed from above. That is usually x,
+            // but a fall that started on this very row has clay or sand over x.
+            if y > 0 {
+                let above = &self.objects[(y - 1) * self.width..y * self.width];
+                let feed = if above[x] == Object::Visited {
+                    Some(x)
+                } else {
+                    (left_end..right_end).find(|&i| above[i] == Object::Visited)
+                };
+
+                if let Some(x) = feed {
+                    jobs.push(Job::Spread { x, y: y - 1 });
+                }
+            }
+        }
+    }
+
+    pub fn count(&self) -> (u32, u32) {
+        let band = &self.objects[self.y_min * self.width..(self.y_max + 1) * self.width];
+
+        band.iter().cloned().fold((0, 0), |(w, v), object| {
+            if object == Object::Water {
+                (w + 1, v)
+            } else if object == Object::Visited {
+                (w, v + 1)
+            } else {
+                (w, v)
+            }
+        })
+    }
+}
+
+pub struct Frame {
+    pub map: String,
+    pub water: u32,
+    pub visited: u32,
+}
+
+pub struct Simulation {
+    map: Map,
+    jobs: Vec<Job>,
+    step: usize,
+}
+
+impl Simulation {
+    pub fn new(map: Map, spring: usize) -> Self {
+        Simulation {
+            map,
+            jobs: vec![Job::Fall { x: spring, y: 0 }],
+            step: 1,
+        }
+    }
+
+    pub fn step(mut self, jobs: usize) -> Self {
+        self.step = jobs.max(1);
+        self
+    }
+
+    pub fn into_map(self) -> Map {
+        self.map
+    }
+}
+
+impl Iterator for Simulation {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.jobs.is_empty() {
+            return None;
+        }
+
+        for _ in 0..self.step {
+            match self.jobs.pop() {
+                Some(job) => self.map.run(job, &mut self.jobs),
+                None => break,
+            }
+        }
+
+        let (water, visited) = self.map.count();
+
+        Some(Frame {
+            map: self.map.to_string(),
+            water,
+            visited,
+        })
+    }
+}
+
+pub fn populate_initial_state(blueprints: Vec<Blueprints>) -> (Map, usize) {
+    let (x_min, y_min, x_max, y_max) = find_extremes(&blueprints);
+    // Keep the spring's column on the map even when all the clay is off to one side.
+    let (x_min, x_max) = (x_min.min(SPRING), x_max.max(SPRING));
+    // Water only spreads over clay or settled water, so it can leave the clay's
+    // x range by at most the one column it drops down; one column of padding
+    // per side is enough however the ledges are stacked.
+    let width = x_max - x_min + 3;
+    let spring = SPRING - x_min + 1;
+    let mut map = Map::new(width, y_min, y_max);
+
+    for (x, y) in blueprints.into_iter().flat_map(move |blueprint| {
+        let (range, num, horizontal) = match blueprint {
+            Blueprints::Vertical { x, y } => (y, x, false),
+            Blueprints::Horizontal { x, y } => (x, y, true),
+        };
+
+        range.map(move |range| {
+            if horizontal {
+                (range - x_min + 1, num)
+            } else {
+                (num - x_min + 1, range)
+            }
+        })
+    }) {
+        map.set(x, y, Object::Clay);
+    }
+
+    (map, spring)
+}
+
+pub fn parse(input: &str) -> Result<Vec<Blueprints>, Error> {
+    input
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+pub fn solve(input: &str) -> Result<(u32, u32), Error> {
+    let (mut map, spring) = populate_initial_state(parse(input)?);
+
+    map.fill(spring, 0);
+
+    let (water, visited) = map.count();
+
+    Ok((water + visited, water))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "x=495, y=2..7
+y=7, x=495..501
+x=501, y=3..7
+x=498, y=2..4
+x=506, y=1..2
+x=498, y=10..13
+x=504, y=10..13
+y=13, x=498..504";
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// The recursive fill the job stack replaced, over sets of coordinates.
+    struct Reference {
+        clay: HashSet<(usize, usize)>,
+        flowing: HashSet<(usize, usize)>,
+        still: HashSet<(usize, usize)>,
+        y_max: usize,
+    }
+
+    impl Reference {
+        fn blocked(&self, x: usize, y: usize) -> bool {
+            self.clay.contains(&(x, y)) || self.still.contains(&(x, y))
+        }
+
+        fn fill(&mut self, x: usize, mut y: usize) {
+            loop {
+                if y > self.y_max {
+                    return;
+                }
+
+                self.flowing.insert((x, y));
+
+                if self.blocked(x, y + 1) {
+                    break;
+                }
+
+                if self.flowing.contains(&(x, y + 1)) {
+                    return;
+                }
+
+                y += 1;
+            }
+
+            loop {
+                let mut edges = Vec::new();
+
+                for &left in &[true, false] {
+                    let mut cx = x;
+
+                    loop {
+                        let next = if left { cx - 1 } else { cx + 1 };
+
+                        if self.blocked(next, y) {
+                            edges.push((cx, true));
+                            break;
+                        }
+
+                        cx = next;
+                        self.flowing.insert((cx, y));
+
+                        if !self.blocked(cx, y + 1) {
+                            if !self.flowing.contains(&(cx, y + 1)) {
+                                self.fill(cx, y + 1);
+                            }
+
+                            if !self.blocked(cx, y + 1) {
+                                edges.push((cx, false));
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                if !(edges[0].1 && edges[1].1) {
+                    return;
+                }
+
+                for cx in edges[0].0..=edges[1].0 {
+                    self.still.insert((cx, y));
+                    self.flowing.remove(&(cx, y));
+                }
+
+                y -= 1;
+                self.flowing.insert((x, y));
+            }
+        }
+    }
+
+    fn reference(input: &str) -> (u32, u32) {
+        let clay: HashSet<_> = parse(input)
+            .unwrap()
+            .into_iter()
+            .flat_map(|blueprint| -> Vec<(usize, usize)> {
+                match blueprint {
+                    Blueprints::Vertical { x, y } => y.map(|y| (x, y)).collect(),
+                    Blueprints::Horizontal { x, y } => x.map(|x| (x, y)).collect(),
+                }
+            })
+            .collect();
+        let y_min = clay.iter().map(|&(_, y)| y).min().unwrap();
+        let y_max = clay.iter().map(|&(_, y)| y).max().unwrap();
+
+        let mut reference = Reference {
+            clay,
+            flowing: HashSet::new(),
+            still: HashSet::new(),
+            y_max,
+        };
+        reference.fill(SPRING, 0);
+
+        let band = |set: &HashSet<(usize, usize)>| {
+            set.iter()
+                .filter(|&&(_, y)| y >= y_min && y <= y_max)
+                .count() as u32
+        };
+        let (flowing, still) = (band(&reference.flowing), band(&reference.still));
+
+        (flowing + still, still)
+    }
+
+    /// Up to ten buckets and ledges scattered under the spring.
+    fn random_input(state: &mut u64) -> String {
+        let mut random = |n: u64| (xorshift(state) % n) as usize;
+        let mut lines = Vec::new();
+
+        for _ in 0..1 + random(10) {
+            let (x, y, w, h) = (
+                470 + random(60),
+                10 + random(70),
+                2 + random(9),
+                1 + random(8),
+            );
+
+            if random(10) < 6 {
+                lines.push(format!("x={}, y={}..{}", x, y - h, y));
+                lines.push(format!(
+                    "x={}, y={}..{}",
+                    x + w,
+                    y - random(h as u64 + 1),
+                    y
+                ));
+            }
+
+            lines.push(format!("y={}, x={}..{}", y, x, x + w));
+        }
+
+        lines.join("\n")
+    }
+
+    #[test]
+    fn deep_shaft_on_a_small_stack() {
+        let input = "x=499, y=2..5000\nx=501, y=3..5000\ny=5000, x=499..501";
+        let (total, water) = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || solve(input).unwrap())
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!((total, water), (9998, 4997));
+    }
+
+    #[test]
+    fn matches_recursive_fill() {
+        assert_eq!(solve(EXAMPLE).unwrap(), reference(EXAMPLE));
+
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+
+        for _ in 0..300 {
+            let input = random_input(&mut state);
+
+            assert_eq!(solve(&input).unwrap(), reference(&input), "{}", input);
+        }
+    }
+
+    fn filled(input: &str) -> Map {
+        let (mut map, spring) = populate_initial_state(parse(input).unwrap());
+        map.fill(spring, 0);
+        map
+    }
+
+    #[test]
+    fn single_grid_counts() {
+        let map = filled(EXAMPLE);
+        assert_eq!(map.objects.len(), map.width * map.depth);
+        assert_eq!(map.count(), (29, 28));
+
+        let input = std::fs::read_to_string("../inputs/input-17-01.txt").unwrap();
+        assert_eq!(solve(&input).unwrap(), (39367, 33061));
+    }
+
+    #[test]
+    fn bucket_flush_against_x_min() {
+        // the bucket's left wall is the leftmost clay, so overflow on that
+        // side lands in the padding column
+        assert_eq!(
+            solve("x=495, y=2..7\ny=7, x=495..501\nx=501, y=3..7").unwrap(),
+            (32, 20)
+        );
+    }
+
+    #[test]
+    fn spring_outside_clay_range() {
+        assert_eq!(
+            solve("x=505, y=2..7\ny=7, x=505..510\nx=510, y=3..7").unwrap(),
+            (6, 0)
+        );
+        assert_eq!(
+            solve("x=490, y=2..7\ny=7, x=490..495\nx=495, y=3..7").unwrap(),
+            (6, 0)
+        );
+    }
+
+    #[test]
+    fn water_runs_off_the_border() {
+        // no padding at all: the floor spans the whole map
+        let mut map = Map::new(5, 0, 2);
+        for x in 0..5 {
+            map.set(x, 2, Object::Clay);
+        }
+        map.fill(2, 0);
+
+        assert_eq!(map.count(), (0, 6));
+        assert_eq!(map.to_string(), "..|..\n|||||\n#####");
+    }
+
+    // The statement's final diagram, with the spring drawn as flowing water.
+    const FINAL: &str = "\
+......|.......
+......|.....#.
+.#..#||||...#.
+.#..#~~#|.....
+.#..#~~#|.....
+.#~~~~~#|.....
+.#~~~~~#|.....
+.#######|.....
+........|.....
+...|||||||||..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#######|..";
+
+    #[test]
+    fn render_text() {
+        assert_eq!(filled(EXAMPLE).to_string(), FINAL);
+    }
+
+    #[test]
+    fn render_ppm() {
+        let mut out = Vec::new();
+        filled(EXAMPLE).write_ppm(&mut out).unwrap();
+
+        let header = b"P6\n14 14\n255\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+        assert_eq!(out.len(), header.len() + 14 * 14 * 3);
+
+        let pixel = |x: usize, y: usize| {
+            let i = header.len() + (y * 14 + x) * 3;
+            [out[i], out[i + 1], out[i + 2]]
+        };
+        assert_eq!(pixel(0, 0), Object::Sand.color());
+        assert_eq!(pixel(6, 1), Object::Visited.color());
+        assert_eq!(pixel(1, 2), Object::Clay.color());
+        assert_eq!(pixel(5, 3), Object::Water.color());
+    }
+
+    #[test]
+    fn only_the_clay_band_is_counted() {
+        assert_eq!(solve(EXAMPLE).unwrap(), (57, 29));
+
+        // the falling column above y = 3 isn't scored
+        assert_eq!(
+            solve("y=5, x=497..503\nx=497, y=3..5\nx=503, y=3..5").unwrap(),
+            (16, 10)
+        );
+        assert_eq!(
+            solve("y=3, x=498..502\nx=496, y=5..8\nx=504, y=5..8\ny=8, x=496..504").unwrap(),
+            (42, 21)
+        );
+    }
+
+    #[test]
+    fn widening_ledges() {
+        // each ledge is wider than the one above, so the water cascades out
+        // to the padding column on both sides
+        let map = filled("y=5, x=498..502\ny=8, x=496..504\ny=11, x=494..506");
+        let (water, visited) = map.count();
+        let render = map.to_string();
+        let rows: Vec<_> = render.lines().collect();
+
+        assert_eq!((water + visited, water), (36, 0));
+        assert_eq!(rows[10], "|||||||||||||||");
+        assert_eq!(rows[11], "|#############|");
+    }
+
+    #[test]
+    fn animation_ends_on_the_final_fill() {
+        for &step in &[1, 3, 1000] {
+            let (map, spring) = populate_initial_state(parse(EXAMPLE).unwrap());
+            let mut simulation = Simulation::new(map, spring).step(step);
+            let frames: Vec<_> = simulation.by_ref().collect();
+            let last = frames.last().unwrap();
+
+            assert_eq!((last.water + last.visited, last.water), (57, 29));
+            assert_eq!(last.map, FINAL);
+            assert_eq!(simulation.into_map().count(), filled(EXAMPLE).count());
+            assert!(frames.windows(2).all(|w| w[0].water <= w[1].water));
+
+            if step == 1000 {
+                assert_eq!(frames.len(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn wide_comb() {
+        // 1334 narrow buckets side by side on a 4000-column floor
+        let mut input = String::from("y=60, x=100..4100\n");
+        for x in (100..=4100).step_by(3) {
+            input.push_str(&format!("x={}, y=10..60\n", x));
+        }
+
+        let start = std::time::Instant::now();
+        let counts = solve(&input).unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(counts, (133403, 133300));
+        assert!(elapsed.as_millis() < 1000, "{:?}", elapsed);
+    }
+
+    #[test]
+    fn solve_example() {
+        assert_eq!(solve(EXAMPLE).unwrap(), (57, 29));
+
+        let crlf = format!("{}\r\n\n", EXAMPLE.replace('\n', "\r\n"));
+        assert_eq!(solve(&crlf).unwrap(), (57, 29));
+    }
+
+    #[test]
+    fn blueprint_orientation() {
+        match "x=495, y=2..7".parse().unwrap() {
+            Blueprints::Vertical { x, y } => assert_eq!((x, y), (495, 2..8)),
+            Blueprints::Horizontal { .. } => panic!("expected a vertical vein"),
+        }
+
+        match "y=7, x=495..501".parse().unwrap() {
+            Blueprints::Horizontal { x, y } => assert_eq!((x, y), (495..502, 7)),
+            Blueprints::Vertical { .. } => panic!("expected a horizontal vein"),
+        }
+
+        match "x=5, y=3..3".parse().unwrap() {
+            Blueprints::Vertical { x, y } => assert_eq!((x, y), (5, 3..4)),
+            Blueprints::Horizontal { .. } => panic!("expected a vertical vein"),
+        }
+    }
+
+    #[test]
+    fn malformed_blueprints() {
+        let error = "x=5, y=3..2".parse::<Blueprints>().err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid input: range 3..2 runs backwards: x=5, y=3..2"
+        );
+        assert!(solve("x=5, y=3..2").is_err());
+
+        for line in &[
+            "x=5, x=3..4",
+            "y=5, y=3..4",
+            "z=5, y=3..4",
+            "x=5 y=3..4",
+            "x=5, y=3.4",
+            "x=a, y=3..4",
+            "x=5, y=3..",
+            "",
+        ] {
+            assert!(line.parse::<Blueprints>().is_err(), "{:?}", line);
+        }
+    }
+
+    /// The same plug applied incrementally and before a from-scratch fill.
+    fn plugged(input: &str, x: usize, y: usize) -> (Map, Map) {
+        let mut incremental = filled(input);
+        incremental.add_clay(x, y);
+        incremental.resimulate_from((x, y));
+
+        let (mut scratch, spring) = populate_initial_state(parse(input).unwrap());
+        scratch.add_clay(x, y);
+        scratch.fill(spring, 0);
+
+        (incremental, scratch)
+    }
+
+    #[test]
+    fn plug_the_open_stream() {
+        // (8, 8) is on the stream falling from the upper bucket's overflow
+        let (incremental, scratch) = plugged(EXAMPLE, 8, 8);
+
+        assert_eq!(incremental.to_string(), scratch.to_string());
+        assert_eq!(incremental.count(), scratch.count());
+        assert_ne!(incremental.count(), (29, 28));
+    }
+
+    #[test]
+    fn plug_every_tile_of_the_example() {
+        let map = filled(EXAMPLE);
+        let mut checked = 0;
+
+        for y in 1..map.depth {
+            for x in 0..map.width {
+                if map.get(x, y) != Object::Clay {
+                    let (incremental, scratch) = plugged(EXAMPLE, x, y);
+
+                    assert_eq!(
+                        incremental.to_string(),
+                        scratch.to_string(),
+                        "plug at {},{}",
+                        x,
+                        y
+                    );
+                    checked += 1;
+                }
+            }
+        }
+
+        assert!(checked > 100, "{}", checked);
+    }
+
+    #[test]
+    fn plug_random_layouts() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+
+        for _ in 0..300 {
+            let input = random_input(&mut state);
+            let map = filled(&input);
+            let wet: Vec<_> = (map.width..map.objects.len())
+                .filter(|&i| map.objects[i].is_wet())
+                .collect();
+
+            if wet.is_empty() {
+                continue;
+            }
+
+            for _ in 0..5 {
+                // mostly plug wet tiles, sometimes anywhere below the spring
+                let i = if xorshift(&mut state).is_multiple_of(4) {
+                    map.width + xorshift(&mut state) as usize % (map.objects.len() - map.width)
+                } else {
+                    wet[xorshift(&mut state) as usize % wet.len()]
+                };
+                let (x, y) = (i % map.width, i / map.width);
+                let (incremental, scratch) = plugged(&input, x, y);
+
+                assert_eq!(
+                    incremental.to_string(),
+                    scratch.to_string(),
+                    "plug at {},{} in\n{}",
+                    x,
+                    y,
+                    input
+                );
+            }
+        }
+    }
+}
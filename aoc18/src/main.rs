@@ -1,20 +1,24 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::{BufRead, BufReader, Error as IoError};
+use std::iter;
 use std::mem;
 use std::path::Path;
+use std::str::FromStr;
 
-const DIM: usize = 50;
 const PART_ONE: usize = 10;
 const PART_TWO: usize = 1_000_000_000;
-const THRESHOLD: usize = 500;
-type Map = [[Object; DIM]; DIM];
 
 #[derive(Debug)]
 enum Error {
     Io(IoError),
     Invalid(String),
+    Usage(String),
 }
 
 impl From<IoError> for Error {
@@ -28,57 +32,175 @@ impl fmt::Display for Error {
         match self {
             Error::Io(e) => fmt::Display::fmt(e, f),
             Error::Invalid(s) => write!(f, "{}", s),
+            Error::Usage(s) => write!(f, "{}", s),
         }
     }
 }
 
-fn parse_input(path: &Path) -> Result<Map, Error> {
-    let mut map = [[Object::OpenGround; DIM]; DIM];
+fn parse_input(path: &Path) -> Result<Grid, Error> {
+    let mut cells = Vec::new();
+    let mut width = None;
 
     for (y, result) in BufReader::new(File::open(path)?).lines().enumerate() {
         let row = result?;
 
-        for (x, object) in row.bytes().enumerate().take(DIM) {
-            match object {
-                b'|' => map[y][x] = Object::Trees,
-                b'#' => map[y][x] = Object::Lumberyard,
-                b'.' => (),
+        match width {
+            None => width = Some(row.len()),
+            Some(width) if width != row.len() => {
+                return Err(Error::Invalid(format!(
+                    "row {} is {} wide, expected {}",
+                    y + 1,
+                    row.len(),
+                    width
+                )))
+            }
+            _ => (),
+        }
+
+        for object in row.bytes() {
+            cells.push(match object {
+                b'|' => Object::Trees,
+                b'#' => Object::Lumberyard,
+                b'.' => Object::OpenGround,
                 b => {
                     return Err(Error::Invalid(format!(
                         "invalid character: {}",
                         char::from(b)
                     )))
                 }
-            }
+            });
         }
     }
 
-    Ok(map)
+    match width {
+        Some(width) if width > 0 => Ok(Grid {
+            width,
+            height: cells.len() / width,
+            cells,
+        }),
+        _ => Err(Error::Invalid("empty grid".into())),
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 enum Object {
     OpenGround,
     Trees,
     Lumberyard,
 }
 
-fn change(map: &Map, x: usize, y: usize) -> Object {
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            Object::OpenGround => '.',
+            Object::Trees => '|',
+            Object::Lumberyard => '#',
+        };
+
+        write!(f, "{}", c)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Object>,
+}
+
+impl Grid {
+    fn row(&self, y: usize) -> &[Object] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+
+            for &object in self.row(y) {
+                write!(f, "{}", object)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// How many of an acre's eight neighbours it takes for open ground to sprout
+// trees, for trees to become a lumberyard, and for a lumberyard to stay one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Ruleset {
+    sprout: u8,
+    build: u8,
+    sustain_lumber: u8,
+    sustain_trees: u8,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            sprout: 3,
+            build: 3,
+            sustain_lumber: 1,
+            sustain_trees: 1,
+        }
+    }
+}
+
+impl FromStr for Ruleset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Usage(format!("invalid rules: {}", s));
+        let thresholds = s
+            .split(',')
+            .map(|t| t.trim().parse().map_err(|_| invalid()))
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        match thresholds[..] {
+            [sprout, build, sustain_lumber, sustain_trees] => Ok(Ruleset {
+                sprout,
+                build,
+                sustain_lumber,
+                sustain_trees,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn change(grid: &Grid, rules: &Ruleset, x: usize, y: usize) -> Object {
     let mut trees = 0;
     let mut lumber = 0;
 
     let (x_min, x_max) = (
         if x == 0 { 0 } else { x - 1 },
-        if x == DIM - 1 { DIM } else { x + 2 },
+        if x == grid.width - 1 {
+            grid.width
+        } else {
+            x + 2
+        },
     );
     let (y_min, y_max) = (
         if y == 0 { 0 } else { y - 1 },
-        if y == DIM - 1 { DIM } else { y + 2 },
+        if y == grid.height - 1 {
+            grid.height
+        } else {
+            y + 2
+        },
     );
 
-    for row in &map[y_min..y_max] {
-        for &object in &row[x_min..x_max] {
-            match object {
+    for ny in y_min..y_max {
+        for nx in x_min..x_max {
+            if (nx, ny) == (x, y) {
+                continue;
+            }
+
+            match grid.row(ny)[nx] {
                 Object::Trees => trees += 1,
                 Object::Lumberyard => lumber += 1,
                 Object::OpenGround => (),
@@ -86,83 +208,958 @@ fn change(map: &Map, x: usize, y: usize) -> Object {
         }
     }
 
-    match map[y][x] {
-        Object::OpenGround if trees > 2 => Object::Trees,
-        Object::Trees if lumber > 2 => Object::Lumberyard,
-        Object::Lumberyard if lumber == 1 || trees == 0 => Object::OpenGround,
+    match grid.row(y)[x] {
+        Object::OpenGround if trees >= rules.sprout => Object::Trees,
+        Object::Trees if lumber >= rules.build => Object::Lumberyard,
+        Object::Lumberyard if lumber < rules.sustain_lumber || trees < rules.sustain_trees => {
+            Object::OpenGround
+        }
         object => object,
     }
 }
 
-fn advance_one_minute(previous: &Map, next: &mut Map) {
-    for (y, row) in next.iter_mut().enumerate() {
-        for (x, object) in row.iter_mut().enumerate() {
-            *object = change(previous, x, y);
-        }
+fn advance_row(previous: &Grid, rules: &Ruleset, y: usize, row: &mut [Object]) {
+    for (x, object) in row.iter_mut().enumerate() {
+        *object = change(previous, rules, x, y);
     }
 }
 
-fn count(map: &Map) -> usize {
+// Every row of `next` only reads `previous`, so the rows can be filled in any
+// order.
+fn advance_one_minute(previous: &Grid, next: &mut Grid, rules: &Ruleset) {
+    #[cfg(feature = "parallel")]
+    let rows = next.cells.par_chunks_mut(previous.width);
+    #[cfg(not(feature = "parallel"))]
+    let rows = next.cells.chunks_mut(previous.width);
+
+    rows.enumerate()
+        .for_each(|(y, row)| advance_row(previous, rules, y, row));
+}
+
+fn count(grid: &Grid) -> (usize, usize) {
     let mut trees = 0;
     let mut lumber = 0;
 
-    for row in map.iter() {
-        for &object in row.iter() {
-            match object {
-                Object::Trees => trees += 1,
-                Object::Lumberyard => lumber += 1,
-                Object::OpenGround => (),
+    for &object in &grid.cells {
+        match object {
+            Object::Trees => trees += 1,
+            Object::Lumberyard => lumber += 1,
+            Object::OpenGround => (),
+        }
+    }
+
+    (trees, lumber)
+}
+
+// The same landscape as two bitplanes, one bit per acre and 64 acres per word,
+// with bit i of word w holding column 64 * w + i.
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct BitGrid {
+    width: usize,
+    height: usize,
+    words: usize,
+    trees: Vec<u64>,
+    lumber: Vec<u64>,
+}
+
+impl BitGrid {
+    fn from_grid(grid: &Grid) -> Self {
+        let words = grid.width.div_ceil(64);
+        let mut trees = vec![0; words * grid.height];
+        let mut lumber = vec![0; words * grid.height];
+
+        for y in 0..grid.height {
+            for (x, &object) in grid.row(y).iter().enumerate() {
+                let (word, bit) = (y * words + x / 64, 1 << (x % 64));
+
+                match object {
+                    Object::Trees => trees[word] |= bit,
+                    Object::Lumberyard => lumber[word] |= bit,
+                    Object::OpenGround => (),
+                }
             }
         }
+
+        BitGrid {
+            width: grid.width,
+            height: grid.height,
+            words,
+            trees,
+            lumber,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Object {
+        let (word, bit) = (y * self.words + x / 64, 1 << (x % 64));
+
+        if self.trees[word] & bit != 0 {
+            Object::Trees
+        } else if self.lumber[word] & bit != 0 {
+            Object::Lumberyard
+        } else {
+            Object::OpenGround
+        }
     }
 
-    trees * lumber
+    fn count(&self) -> (usize, usize) {
+        let ones = |plane: &[u64]| plane.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+
+        (ones(&self.trees), ones(&self.lumber))
+    }
 }
 
-fn advance_time_naive(map: &mut Map) -> usize {
-    let mut next = &mut map.clone();
-    let mut previous = map;
-    for _ in 0..PART_ONE {
-        advance_one_minute(previous, next);
-        mem::swap(&mut previous, &mut next);
+impl fmt::Display for BitGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+
+            for x in 0..self.width {
+                write!(f, "{}", self.get(x, y))?;
+            }
+        }
+
+        Ok(())
     }
+}
 
-    count(previous)
+// Per-bit count of how many neighbours are set, one bitplane per binary digit;
+// eight neighbours fit in four digits.
+#[derive(Default)]
+struct Counter {
+    digits: [u64; 4],
 }
 
-fn advance_time_oracle(map: &mut Map) -> usize {
-    let mut next = &mut map.clone();
-    let mut previous = map;
-    for _ in PART_ONE..THRESHOLD {
-        advance_one_minute(previous, next);
+impl Counter {
+    fn add(&mut self, bits: u64) {
+        let mut carry = bits;
 
-        mem::swap(&mut previous, &mut next);
+        for digit in self.digits.iter_mut() {
+            let next = *digit & carry;
+            *digit ^= carry;
+            carry = next;
+        }
     }
 
+    // The bits whose count is at least n, comparing digits from the top.
+    fn at_least(&self, n: u8) -> u64 {
+        let (mut greater, mut equal) = (0, !0);
+
+        for (i, &digit) in self.digits.iter().enumerate().rev() {
+            let wanted = if n >> i & 1 == 1 { !0 } else { 0 };
+            greater |= equal & digit & !wanted;
+            equal &= !(digit ^ wanted);
+        }
+
+        if n >> self.digits.len() != 0 {
+            0
+        } else {
+            greater | equal
+        }
+    }
+}
+
+// Columns x - 1, x and x + 1 of word w, lined up on column x's bit.
+fn shifted(row: &[u64], w: usize) -> (u64, u64, u64) {
+    let current = row[w];
+    let before = if w == 0 { 0 } else { row[w - 1] };
+    let after = row.get(w + 1).cloned().unwrap_or(0);
+
+    (
+        (current << 1) | (before >> 63),
+        current,
+        (current >> 1) | (after << 63),
+    )
+}
+
+fn advance_row_bits(
+    previous: &BitGrid,
+    rules: &Ruleset,
+    y: usize,
+    trees_row: &mut [u64],
+    lumber_row: &mut [u64],
+) {
+    let words = previous.words;
+    let tail = match previous.width % 64 {
+        0 => !0,
+        bits => (1 << bits) - 1,
+    };
+    let rows = if y == 0 { 0 } else { y - 1 }..(y + 2).min(previous.height);
+
+    for w in 0..words {
+        let (mut trees, mut lumber) = (Counter::default(), Counter::default());
+
+        for r in rows.clone() {
+            let plane = r * words..(r + 1) * words;
+            let (left, middle, right) = shifted(&previous.trees[plane.clone()], w);
+            trees.add(left);
+            trees.add(right);
+
+            let (lleft, lmiddle, lright) = shifted(&previous.lumber[plane], w);
+            lumber.add(lleft);
+            lumber.add(lright);
+
+            if r != y {
+                trees.add(middle);
+                lumber.add(lmiddle);
+            }
+        }
+
+        let i = y * words + w;
+        let (tree, yard) = (previous.trees[i], previous.lumber[i]);
+        let open = !(tree | yard);
+        let mask = if w == words - 1 { tail } else { !0 };
+
+        let sprout = open & trees.at_least(rules.sprout);
+        let build = tree & lumber.at_least(rules.build);
+        let sustain =
+            yard & lumber.at_least(rules.sustain_lumber) & trees.at_least(rules.sustain_trees);
+
+        trees_row[w] = mask & (sprout | (tree & !build));
+        lumber_row[w] = mask & (build | sustain);
+    }
+}
+
+fn advance_one_minute_bits(previous: &BitGrid, next: &mut BitGrid, rules: &Ruleset) {
+    let words = previous.words;
+
+    #[cfg(feature = "parallel")]
+    let rows = next
+        .trees
+        .par_chunks_mut(words)
+        .zip(next.lumber.par_chunks_mut(words));
+    #[cfg(not(feature = "parallel"))]
+    let rows = next
+        .trees
+        .chunks_mut(words)
+        .zip(next.lumber.chunks_mut(words));
+
+    rows.enumerate().for_each(|(y, (trees_row, lumber_row))| {
+        advance_row_bits(previous, rules, y, trees_row, lumber_row)
+    });
+}
+
+trait Landscape: Clone + Eq + Hash + fmt::Display {
+    fn advance(&self, next: &mut Self, rules: &Ruleset);
+    fn count(&self) -> (usize, usize);
+}
+
+impl Landscape for Grid {
+    fn advance(&self, next: &mut Self, rules: &Ruleset) {
+        advance_one_minute(self, next, rules);
+    }
+
+    fn count(&self) -> (usize, usize) {
+        count(self)
+    }
+}
+
+impl Landscape for BitGrid {
+    fn advance(&self, next: &mut Self, rules: &Ruleset) {
+        advance_one_minute_bits(self, next, rules);
+    }
+
+    fn count(&self) -> (usize, usize) {
+        BitGrid::count(self)
+    }
+}
+
+// The minute, the wooded acres, the lumberyards and the resource value.
+type Counts = (usize, usize, usize, usize);
+
+struct Simulation<L> {
+    current: L,
+    next: L,
+    rules: Ruleset,
+    minute: usize,
+}
+
+impl<L: Landscape> Simulation<L> {
+    fn new(landscape: L) -> Self {
+        Simulation {
+            next: landscape.clone(),
+            current: landscape,
+            rules: Ruleset::default(),
+            minute: 0,
+        }
+    }
+
+    fn rules(mut self, rules: Ruleset) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn landscape(&self) -> &L {
+        &self.current
+    }
+
+    // Counts the current minute, then moves on to the next one.
+    fn step(&mut self) -> Counts {
+        let (trees, lumber) = self.current.count();
+        let counts = (self.minute, trees, lumber, trees * lumber);
+
+        self.current.advance(&mut self.next, &self.rules);
+        mem::swap(&mut self.current, &mut self.next);
+        self.minute += 1;
+
+        counts
+    }
+
+    fn counts(&mut self) -> impl Iterator<Item = Counts> + '_ {
+        iter::from_fn(move || Some(self.step()))
+    }
+}
+
+fn advance_time_naive<L: Landscape>(landscape: &L, rules: Ruleset, minutes: usize) -> usize {
+    let (_, _, _, value) = Simulation::new(landscape.clone())
+        .rules(rules)
+        .counts()
+        .nth(minutes)
+        .unwrap();
+
+    value
+}
+
+struct History {
+    values: Vec<usize>,
+    cycle_start: usize,
+}
+
+impl History {
+    fn value_at(&self, minute: usize) -> usize {
+        if minute < self.values.len() {
+            return self.values[minute];
+        }
+
+        let cycle = self.values.len() - self.cycle_start;
+
+        self.values[self.cycle_start + (minute - self.cycle_start) % cycle]
+    }
+}
+
+fn advance_time<L: Landscape>(landscape: &L, rules: Ruleset) -> History {
+    let mut simulation = Simulation::new(landscape.clone()).rules(rules);
     let mut times = HashMap::new();
     let mut values = Vec::new();
 
-    for t in THRESHOLD..PART_TWO {
-        let value = count(previous);
+    // Different landscapes can share a resource value, so only a repeat of
+    // the whole grid proves we're in the cycle.
+    loop {
+        let landscape = simulation.landscape().clone();
+
+        if let Some(cycle_start) = times.insert(landscape, values.len()) {
+            return History {
+                values,
+                cycle_start,
+            };
+        }
+
+        let (_, _, _, value) = simulation.step();
         values.push(value);
+    }
+}
 
-        if let Some(tt) = times.insert(value, t) {
-            return values[tt + (PART_TWO - t) % (t - tt) - THRESHOLD];
+// Frames in the puzzle's own layout, from the initial state through `minutes`.
+fn animate<L: Landscape>(landscape: &L, rules: Ruleset, minutes: usize) {
+    let mut simulation = Simulation::new(landscape.clone()).rules(rules);
+
+    for minute in 0..=minutes {
+        match minute {
+            0 => println!("Initial state:"),
+            1 => println!("After 1 minute:"),
+            _ => println!("After {} minutes:", minute),
         }
 
-        advance_one_minute(previous, next);
+        println!("{}\n", simulation.landscape());
+        simulation.step();
+    }
+}
+
+fn solve<L: Landscape>(landscape: L, rules: Ruleset, counts: usize, animation: Option<usize>) {
+    if let Some(minutes) = animation {
+        animate(&landscape, rules, minutes);
+    }
+
+    let mut simulation = Simulation::new(landscape.clone()).rules(rules);
 
-        mem::swap(&mut previous, &mut next);
+    for (minute, trees, lumber, value) in simulation.counts().take(counts) {
+        println!("{} {} {} {}", minute, trees, lumber, value);
     }
 
-    count(previous)
+    println!(
+        "Part 1: {}",
+        advance_time_naive(&landscape, rules, PART_ONE)
+    );
+    println!(
+        "Part 2: {}",
+        advance_time(&landscape, rules).value_at(PART_TWO)
+    );
+}
+
+fn parse_flag(flag: &str, value: Option<String>) -> Result<usize, Error> {
+    let value = value.ok_or_else(|| Error::Usage(format!("{} needs a value", flag)))?;
+
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value for {}: {}", flag, value)))
+}
+
+// Without --threads rayon sizes its pool from RAYON_NUM_THREADS, falling back
+// to one thread per core.
+#[cfg(feature = "parallel")]
+fn set_threads(threads: usize) -> Result<(), Error> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .map_err(|e| Error::Usage(e.to_string()))
+}
+
+#[cfg(not(feature = "parallel"))]
+fn set_threads(_: usize) -> Result<(), Error> {
+    Err(Error::Usage(
+        "--threads needs the parallel feature".to_string(),
+    ))
 }
 
 fn main() -> Result<(), Error> {
     let path = Path::new("inputs/input-18-01.txt");
 
-    let mut map = parse_input(path)?;
-    println!("Part 1: {:?}", advance_time_naive(&mut map));
-    println!("Part 2: {:?}", advance_time_oracle(&mut map));
+    let mut args = env::args().skip(1);
+    let (mut scalar, mut counts, mut animation) = (false, 0, None);
+    let mut rules = Ruleset::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scalar" => scalar = true,
+            "--counts" => counts = parse_flag(&arg, args.next())?,
+            "--animate" => animation = Some(parse_flag(&arg, args.next())?),
+            "--rules" => {
+                rules = args
+                    .next()
+                    .ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))?
+                    .parse()?
+            }
+            "--threads" => set_threads(parse_flag(&arg, args.next())?)?,
+            _ => return Err(Error::Usage(format!("unknown argument: {}", arg))),
+        }
+    }
+
+    let grid = parse_input(path)?;
+
+    if scalar {
+        solve(grid, rules, counts, animation);
+    } else {
+        solve(BitGrid::from_grid(&grid), rules, counts, animation);
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// `density` percent trees, as many lumberyards, the rest open ground.
+    fn random_grid(mut state: u64, density: u64, width: usize, height: usize) -> Grid {
+        let cells = (0..width * height)
+            .map(|_| match xorshift(&mut state) % 100 {
+                r if r < density => Object::Trees,
+                r if r < 2 * density => Object::Lumberyard,
+                _ => Object::OpenGround,
+            })
+            .collect();
+
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    fn value(grid: &Grid) -> usize {
+        let (trees, lumber) = count(grid);
+        trees * lumber
+    }
+
+    /// Steps one grid at a time until one repeats, returning every value
+    /// seen and the minute the cycle starts.
+    fn brute_force(grid: &Grid) -> (Vec<usize>, usize) {
+        let mut seen = HashMap::new();
+        let mut values = Vec::new();
+        let (mut current, mut next) = (grid.clone(), grid.clone());
+
+        loop {
+            if let Some(start) = seen.insert(current.clone(), values.len()) {
+                return (values, start);
+            }
+
+            values.push(value(&current));
+            advance_one_minute(&current, &mut next, &Ruleset::default());
+            mem::swap(&mut current, &mut next);
+        }
+    }
+
+    fn truth(grid: &Grid, target: usize) -> usize {
+        let (values, start) = brute_force(grid);
+
+        match values.get(target) {
+            Some(&value) => value,
+            None => values[start + (target - start) % (values.len() - start)],
+        }
+    }
+
+    /// The old cycle detection, which took a repeated resource value to mean
+    /// a repeated grid.
+    fn value_keyed(grid: &Grid, target: usize) -> usize {
+        let mut times = HashMap::new();
+        let mut values = Vec::new();
+        let (mut current, mut next) = (grid.clone(), grid.clone());
+
+        for minute in 0..target {
+            let value = value(&current);
+            values.push(value);
+
+            if let Some(start) = times.insert(value, minute) {
+                return values[start + (target - start) % (minute - start)];
+            }
+
+            advance_one_minute(&current, &mut next, &Ruleset::default());
+            mem::swap(&mut current, &mut next);
+        }
+
+        value(&current)
+    }
+
+    #[test]
+    fn shared_resource_values() {
+        let grid = random_grid(7 * 7919, 20, 50, 50);
+        let history = advance_time(&grid, Ruleset::default());
+
+        // two different grids share a resource value before the real cycle
+        let repeat = history
+            .values
+            .iter()
+            .enumerate()
+            .position(|(i, v)| history.values[..i].contains(v))
+            .unwrap();
+        assert!(repeat < history.cycle_start, "{}", repeat);
+        assert_ne!(value_keyed(&grid, PART_TWO), truth(&grid, PART_TWO));
+
+        assert_eq!(history.value_at(PART_TWO), truth(&grid, PART_TWO));
+        assert_eq!(
+            advance_time(&BitGrid::from_grid(&grid), Ruleset::default()).value_at(PART_TWO),
+            truth(&grid, PART_TWO)
+        );
+    }
+
+    #[test]
+    fn history_matches_brute_force() {
+        for seed in 1..4 {
+            for &density in &[5, 20, 35] {
+                let grid = random_grid(seed * 7919, density, 50, 50);
+                let history = advance_time(&grid, Ruleset::default());
+                let bits = advance_time(&BitGrid::from_grid(&grid), Ruleset::default());
+
+                let (values, start) = brute_force(&grid);
+
+                for &minute in &[0, 1, 10, 499, 500, 1000, 12345, PART_TWO] {
+                    let expected = match values.get(minute) {
+                        Some(&value) => value,
+                        None => values[start + (minute - start) % (values.len() - start)],
+                    };
+
+                    assert_eq!(history.value_at(minute), expected);
+                    assert_eq!(bits.value_at(minute), expected);
+                }
+
+                for &minute in &[0, 5, 10, 300] {
+                    assert_eq!(
+                        advance_time_naive(&grid, Ruleset::default(), minute),
+                        history.value_at(minute)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn real_input() {
+        let grid = parse_input(Path::new("../inputs/input-18-01.txt")).unwrap();
+
+        for history in &[
+            advance_time(&grid, Ruleset::default()),
+            advance_time(&BitGrid::from_grid(&grid), Ruleset::default()),
+        ] {
+            assert_eq!(history.value_at(PART_ONE), 394420);
+            assert_eq!(history.value_at(PART_TWO), 174420);
+        }
+    }
+
+    #[test]
+    fn cycle_after_the_old_warmup() {
+        // this grid only starts repeating at minute 648
+        let grid = random_grid(4 * 7919, 15, 50, 50);
+        let history = advance_time(&BitGrid::from_grid(&grid), Ruleset::default());
+
+        assert_eq!(history.cycle_start, 648);
+        assert_eq!(history.value_at(PART_TWO), truth(&grid, PART_TWO));
+    }
+
+    #[test]
+    fn bits_match_scalar() {
+        let sizes = [
+            (50, 50),
+            (1, 1),
+            (1, 9),
+            (9, 1),
+            (63, 7),
+            (64, 3),
+            (65, 65),
+            (128, 2),
+            (130, 40),
+        ];
+
+        for (i, &(width, height)) in sizes.iter().enumerate() {
+            for &density in &[10, 25, 40] {
+                let mut grid = random_grid(i as u64 * 31 + density + 1, density, width, height);
+                let mut next = grid.clone();
+                let mut bits = BitGrid::from_grid(&grid);
+                let mut bits_next = bits.clone();
+
+                for minute in 0..100 {
+                    assert!(
+                        bits == BitGrid::from_grid(&grid),
+                        "{}x{} minute {}",
+                        width,
+                        height,
+                        minute
+                    );
+                    assert_eq!(bits.count(), count(&grid));
+
+                    advance_one_minute(&grid, &mut next, &Ruleset::default());
+                    mem::swap(&mut grid, &mut next);
+                    advance_one_minute_bits(&bits, &mut bits_next, &Ruleset::default());
+                    mem::swap(&mut bits, &mut bits_next);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn counter_thresholds() {
+        for neighbours in 0..256u32 {
+            let mut counter = Counter::default();
+
+            // the lowest and highest bit of each word see the same neighbours
+            for i in 0..8 {
+                counter.add(if neighbours >> i & 1 == 1 {
+                    1 | 1 << 63
+                } else {
+                    0
+                });
+            }
+
+            let n = neighbours.count_ones() as u8;
+
+            for threshold in 0..=20 {
+                let bits = counter.at_least(threshold);
+
+                assert_eq!(bits & 1 == 1, n >= threshold);
+                assert_eq!(bits >> 63 == 1, n >= threshold);
+                assert_eq!(bits & 2 != 0, threshold == 0);
+            }
+        }
+    }
+
+    #[test]
+    fn bits_speedup() {
+        let grid = random_grid(99, 30, 1000, 1000);
+
+        let (mut scalar, mut next) = (grid.clone(), grid.clone());
+        let start = Instant::now();
+        for _ in 0..10 {
+            advance_one_minute(&scalar, &mut next, &Ruleset::default());
+            mem::swap(&mut scalar, &mut next);
+        }
+        let scalar_time = start.elapsed();
+
+        let (mut bits, mut next) = (BitGrid::from_grid(&grid), BitGrid::from_grid(&grid));
+        let start = Instant::now();
+        for _ in 0..10 {
+            advance_one_minute_bits(&bits, &mut next, &Ruleset::default());
+            mem::swap(&mut bits, &mut next);
+        }
+        let bits_time = start.elapsed();
+
+        assert!(bits == BitGrid::from_grid(&scalar));
+        assert!(
+            bits_time * 5 < scalar_time,
+            "bits {:?}, scalar {:?}",
+            bits_time,
+            scalar_time
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_serial() {
+        let run = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+
+            pool.install(|| {
+                assert_eq!(rayon::current_num_threads(), threads);
+
+                let grid = random_grid(500, 30, 500, 500);
+                let (mut scalar, mut next) = (grid.clone(), grid.clone());
+                let (mut bits, mut bits_next) =
+                    (BitGrid::from_grid(&grid), BitGrid::from_grid(&grid));
+
+                for _ in 0..50 {
+                    advance_one_minute(&scalar, &mut next, &Ruleset::default());
+                    mem::swap(&mut scalar, &mut next);
+                    advance_one_minute_bits(&bits, &mut bits_next, &Ruleset::default());
+                    mem::swap(&mut bits, &mut bits_next);
+                }
+
+                (scalar, bits)
+            })
+        };
+
+        let (serial, parallel) = (run(1), run(4));
+
+        assert!(serial.0.cells == parallel.0.cells);
+        assert!(serial.1 == parallel.1);
+        assert!(serial.1 == BitGrid::from_grid(&serial.0));
+    }
+
+    const EXAMPLE: &str = ".#.#...|#.
+.....#|##|
+.|..|...#.
+..|#.....#
+#.#|||#|#|
+...#.||...
+.|....|...
+||...#|.#|
+|.||||..|.
+...#.|..|.
+";
+
+    fn example(name: &str) -> Grid {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, EXAMPLE).unwrap();
+        parse_input(&path).unwrap()
+    }
+
+    #[test]
+    fn example_counts() {
+        let grid = example("aoc18-example-counts.txt");
+        let counts: Vec<_> = Simulation::new(grid.clone()).counts().take(11).collect();
+        let bits: Vec<_> = Simulation::new(BitGrid::from_grid(&grid))
+            .counts()
+            .take(11)
+            .collect();
+
+        assert_eq!(counts, bits);
+        assert!(counts.iter().enumerate().all(|(i, c)| c.0 == i));
+        assert_eq!(counts[0], (0, 27, 17, 27 * 17));
+        assert_eq!(counts[10], (10, 37, 31, 1147));
+
+        assert_eq!(advance_time_naive(&grid, Ruleset::default(), 10), 1147);
+        assert_eq!(advance_time(&grid, Ruleset::default()).value_at(10), 1147);
+    }
+
+    const AFTER_1: &str = ".......##.
+......|###
+.|..|...#.
+..|#||...#
+..##||.|#|
+...#||||..
+||...|||..
+|||||.||.|
+||||||||||
+....||..|.";
+
+    const AFTER_10: &str = ".||##.....
+||###.....
+||##......
+|##.....##
+|##.....##
+|##....##|
+||##.####|
+||#####|||
+||||#|||||
+||||||||||";
+
+    #[test]
+    fn example_frames() {
+        let grid = example("aoc18-example-frames.txt");
+        assert_eq!(format!("{}\n", grid), EXAMPLE);
+
+        let mut scalar = Simulation::new(grid.clone());
+        let mut bits = Simulation::new(BitGrid::from_grid(&grid));
+        assert_eq!(bits.landscape().to_string(), grid.to_string());
+
+        for minute in 1..=10 {
+            scalar.step();
+            bits.step();
+
+            assert_eq!(scalar.landscape().to_string(), bits.landscape().to_string());
+
+            if minute == 1 {
+                assert_eq!(scalar.landscape().to_string(), AFTER_1);
+            }
+        }
+
+        assert_eq!(scalar.landscape().to_string(), AFTER_10);
+    }
+
+    /// A 3x3 grid from three space-separated rows.
+    fn fixture(rows: &str) -> Grid {
+        let cells = rows
+            .bytes()
+            .filter(|b| !b.is_ascii_whitespace())
+            .map(|b| match b {
+                b'|' => Object::Trees,
+                b'#' => Object::Lumberyard,
+                _ => Object::OpenGround,
+            })
+            .collect();
+
+        Grid {
+            width: 3,
+            height: 3,
+            cells,
+        }
+    }
+
+    /// The centre acre after one minute, checked against the bitplanes too.
+    fn centre(rows: &str) -> Object {
+        let grid = fixture(rows);
+        let rules = Ruleset::default();
+        let scalar = change(&grid, &rules, 1, 1);
+
+        let bits = BitGrid::from_grid(&grid);
+        let mut next = bits.clone();
+        advance_one_minute_bits(&bits, &mut next, &rules);
+        assert!(next.get(1, 1) == scalar, "{}", rows);
+
+        scalar
+    }
+
+    #[test]
+    fn sprout_rule() {
+        assert!(centre("||. |.. ...") == Object::Trees);
+        assert!(centre("||. ... ...") == Object::OpenGround);
+        assert!(centre("|#| #.# |.#") == Object::Trees);
+    }
+
+    #[test]
+    fn build_rule() {
+        assert!(centre("##. #|. ...") == Object::Lumberyard);
+        assert!(centre("##. .|. ...") == Object::Trees);
+        assert!(centre("##. ||| |||") == Object::Trees);
+    }
+
+    #[test]
+    fn sustain_rule() {
+        // another lumberyard and a tree; the acre itself doesn't count
+        assert!(centre("#|. .#. ...") == Object::Lumberyard);
+        assert!(centre("... .#| ..#") == Object::Lumberyard);
+        assert!(centre("||| |#| |||") == Object::OpenGround);
+        assert!(centre("### ### ###") == Object::OpenGround);
+        assert!(centre("... .#. ...") == Object::OpenGround);
+        assert!(centre("#.. .#. ...") == Object::OpenGround);
+        assert!(centre("|.. .#. ...") == Object::OpenGround);
+    }
+
+    #[test]
+    fn old_formula_is_the_default_ruleset() {
+        let objects = [Object::OpenGround, Object::Trees, Object::Lumberyard];
+
+        for code in 0..3usize.pow(9) {
+            let cells: Vec<_> = (0..9).map(|i| objects[code / 3usize.pow(i) % 3]).collect();
+
+            // the old counts included the acre itself
+            let trees = cells.iter().filter(|&&o| o == Object::Trees).count();
+            let lumber = cells.iter().filter(|&&o| o == Object::Lumberyard).count();
+            let old = match cells[4] {
+                Object::OpenGround if trees > 2 => Object::Trees,
+                Object::Trees if lumber > 2 => Object::Lumberyard,
+                Object::Lumberyard if lumber == 1 || trees == 0 => Object::OpenGround,
+                object => object,
+            };
+
+            let grid = Grid {
+                width: 3,
+                height: 3,
+                cells,
+            };
+            assert!(change(&grid, &Ruleset::default(), 1, 1) == old, "{}", grid);
+        }
+    }
+
+    #[test]
+    fn custom_rules_bits_match_scalar() {
+        let mut state = 12345;
+        let mut random = |n: u64| xorshift(&mut state) % n;
+
+        for case in 0..60 {
+            let rules = Ruleset {
+                sprout: random(10) as u8,
+                build: random(10) as u8,
+                sustain_lumber: random(10) as u8,
+                sustain_trees: random(10) as u8,
+            };
+            let (width, height) = (1 + random(140) as usize, 1 + random(30) as usize);
+            let mut grid = random_grid(case + 1, 10 + random(30), width, height);
+            let mut next = grid.clone();
+            let mut bits = BitGrid::from_grid(&grid);
+            let mut bits_next = bits.clone();
+
+            for _ in 0..30 {
+                advance_one_minute(&grid, &mut next, &rules);
+                mem::swap(&mut grid, &mut next);
+                advance_one_minute_bits(&bits, &mut bits_next, &rules);
+                mem::swap(&mut bits, &mut bits_next);
+
+                assert!(
+                    bits == BitGrid::from_grid(&grid),
+                    "{:?} {}x{}",
+                    rules,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rules_flag() {
+        assert_eq!("3,3,1,1".parse::<Ruleset>().unwrap(), Ruleset::default());
+        assert_eq!(
+            " 4, 2,0 ,9".parse::<Ruleset>().unwrap(),
+            Ruleset {
+                sprout: 4,
+                build: 2,
+                sustain_lumber: 0,
+                sustain_trees: 9,
+            }
+        );
+
+        for rules in &["3,3,1", "3,3,1,x", "3,3,1,1,1", "3,3,1,256", ""] {
+            assert!(rules.parse::<Ruleset>().is_err(), "{:?}", rules);
+        }
+    }
+}
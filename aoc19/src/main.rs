@@ -5,31 +5,9 @@ use std::num::ParseIntError;
 use std::path::Path;
 use std::str::FromStr;
 
-const INSTRUCTION_COUNT: usize = 16;
 const REGISTER_SIZE: usize = 6;
 
-type Registers = [usize; REGISTER_SIZE];
-type OpCode = fn(&mut Registers, usize, usize, usize);
-type InstructionSet = [OpCode; INSTRUCTION_COUNT];
-
-const INSTRUCTIONS: InstructionSet = [
-    |reg, a, b, c| reg[c] = reg[a] + reg[b],
-    |reg, a, b, c| reg[c] = reg[a] + b,
-    |reg, a, b, c| reg[c] = reg[a] * reg[b],
-    |reg, a, b, c| reg[c] = reg[a] * b,
-    |reg, a, b, c| reg[c] = reg[a] & reg[b],
-    |reg, a, b, c| reg[c] = reg[a] & b,
-    |reg, a, b, c| reg[c] = reg[a] | reg[b],
-    |reg, a, b, c| reg[c] = reg[a] | b,
-    |reg, a, _, c| reg[c] = reg[a],
-    |reg, a, _, c| reg[c] = a,
-    |reg, a, b, c| reg[c] = if a > reg[b] { 1 } else { 0 },
-    |reg, a, b, c| reg[c] = if b < reg[a] { 1 } else { 0 },
-    |reg, a, b, c| reg[c] = if reg[a] > reg[b] { 1 } else { 0 },
-    |reg, a, b, c| reg[c] = if a == reg[b] { 1 } else { 0 },
-    |reg, a, b, c| reg[c] = if b == reg[a] { 1 } else { 0 },
-    |reg, a, b, c| reg[c] = if reg[a] == reg[b] { 1 } else { 0 },
-];
+type Registers = [u64; REGISTER_SIZE];
 
 #[derive(Debug)]
 enum Error {
@@ -62,15 +40,15 @@ impl From<String> for Error {
     }
 }
 
-fn factor_sum(mut num: usize) -> usize {
-    let sqrt = (num as f64).sqrt() as usize + 1;
+fn factor_sum(mut num: u64) -> u64 {
+    let sqrt = (num as f64).sqrt() as u64 + 1;
     let mut res = 1;
 
     for i in 2..sqrt {
         let mut sum = 1;
         let mut term = 1;
 
-        while num % i == 0 {
+        while num.is_multiple_of(i) {
             num /= i;
             term *= i;
             sum += term;
@@ -100,24 +78,31 @@ impl Device {
         }
     }
 
-    fn execute(&mut self, instructions: &[Instruction]) -> usize {
-        while let Some(instruction) = instructions.get(self.registers[self.instruction_pointer]) {
+    fn apply(&mut self, instruction: &Instruction) {
+        elfcode::execute(
+            instruction.opcode,
+            &mut self.registers,
+            instruction.a,
+            instruction.b,
+            instruction.c,
+        );
+    }
+
+    fn execute(&mut self, instructions: &[Instruction]) -> u64 {
+        while let Some(instruction) =
+            instructions.get(self.registers[self.instruction_pointer] as usize)
+        {
             if instruction.opcode == 15 {
                 return factor_sum(if instruction.a == instruction.b {
-                    self.registers[instruction.c]
+                    self.registers[instruction.c as usize]
                 } else if instruction.a == instruction.c {
-                    self.registers[instruction.b]
+                    self.registers[instruction.b as usize]
                 } else {
-                    self.registers[instruction.a]
+                    self.registers[instruction.a as usize]
                 });
             }
 
-            INSTRUCTIONS[instruction.opcode](
-                &mut self.registers,
-                instruction.a,
-                instruction.b,
-                instruction.c,
-            );
+            self.apply(instruction);
 
             self.registers[self.instruction_pointer] += 1;
         }
@@ -153,9 +138,9 @@ impl FromStr for Device {
 #[derive(Debug)]
 struct Instruction {
     opcode: usize,
-    a: usize,
-    b: usize,
-    c: usize,
+    a: u64,
+    b: u64,
+    c: u64,
 }
 
 impl FromStr for Instruction {
@@ -171,25 +156,10 @@ impl FromStr for Instruction {
             _ => return Err(Error::from("invalid instruction syntax")),
         };
 
-        let opcode = match opcode {
-            "addr" => 0,
-            "addi" => 1,
-            "mulr" => 2,
-            "muli" => 3,
-            "banr" => 4,
-            "bani" => 5,
-            "borr" => 6,
-            "bori" => 7,
-            "setr" => 8,
-            "seti" => 9,
-            "gtir" => 10,
-            "gtri" => 11,
-            "gtrr" => 12,
-            "eqir" => 13,
-            "eqri" => 14,
-            "eqrr" => 15,
-            opcode => return Err(Error::Invalid(format!("invalid opcode {}", opcode))),
-        };
+        let opcode = elfcode::MNEMONICS
+            .iter()
+            .position(|&mnemonic| mnemonic == opcode)
+            .ok_or_else(|| Error::Invalid(format!("invalid opcode {}", opcode)))?;
 
         Ok(Instruction { opcode, a, b, c })
     }
@@ -233,3 +203,69 @@ fn main() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc16::{build_sets, Data, Operation};
+
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn matches_day_16() {
+        let mut state = 99;
+        let mut random = |n: u64| xorshift(&mut state) % n;
+
+        for _ in 0..10_000 {
+            let opcode = random(16) as usize;
+            let limit = if random(2) == 0 { 8 } else { 1 << 20 };
+            let (a_register, b_register) = elfcode::REGISTER_OPERANDS[opcode];
+            let a = if a_register { random(6) } else { random(limit) };
+            let b = if b_register { random(6) } else { random(limit) };
+            let c = random(6);
+
+            let mut device = Device {
+                registers: [0; REGISTER_SIZE],
+                instruction_pointer: 0,
+            };
+            for r in device.registers.iter_mut() {
+                *r = random(limit);
+            }
+            let before = device.registers;
+
+            let line = format!("{} {} {} {}", elfcode::MNEMONICS[opcode], a, b, c);
+            device.apply(&line.parse().unwrap());
+
+            // day 16 agrees if it finds that `opcode` explains the sample
+            let data = [Data {
+                before: aoc16::Device(before.to_vec()),
+                after: aoc16::Device(device.registers.to_vec()),
+                operation: Operation {
+                    opcode,
+                    a: a as u32,
+                    b: b as u32,
+                    c: c as u32,
+                },
+            }];
+            let (sets, _, _) = build_sets(&data, 1);
+
+            assert!(sets[opcode].contains(&opcode), "{} {:?}", line, before);
+        }
+    }
+
+    #[test]
+    fn real_input() {
+        let (mut device, instructions) =
+            parse_input(Path::new("../inputs/input-19-01.txt")).unwrap();
+        let part_one = device.execute(&instructions);
+
+        device.reset();
+
+        assert_eq!((part_one, device.execute(&instructions)), (1440, 15827040));
+    }
+}
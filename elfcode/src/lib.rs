@@ -0,0 +1,146 @@
+pub const INSTRUCTION_COUNT: usize = 16;
+
+pub const MNEMONICS: [&str; INSTRUCTION_COUNT] = [
+    "addr", "addi", "mulr", "muli", "banr", "bani", "borr", "bori", "setr", "seti", "gtir", "gtri",
+    "gtrr", "eqir", "eqri", "eqrr",
+];
+
+/// Whether operands `a` and `b` name registers, aligned with `MNEMONICS`.
+pub const REGISTER_OPERANDS: [(bool, bool); INSTRUCTION_COUNT] = [
+    (true, true),
+    (true, false),
+    (true, true),
+    (true, false),
+    (true, true),
+    (true, false),
+    (true, true),
+    (true, false),
+    (true, false),
+    (false, false),
+    (false, true),
+    (true, false),
+    (true, true),
+    (false, true),
+    (true, false),
+    (true, true),
+];
+
+/// Runs instruction `op` (an index into `MNEMONICS`). Panics if `op` is out of
+/// range or a register operand doesn't fit in `regs`.
+pub fn execute(op: usize, regs: &mut [u64], a: u64, b: u64, c: u64) {
+    let reg = |x: u64| regs[x as usize];
+
+    let value = match op {
+        0 => reg(a).wrapping_add(reg(b)),
+        1 => reg(a).wrapping_add(b),
+        2 => reg(a).wrapping_mul(reg(b)),
+        3 => reg(a).wrapping_mul(b),
+        4 => reg(a) & reg(b),
+        5 => reg(a) & b,
+        6 => reg(a) | reg(b),
+        7 => reg(a) | b,
+        8 => reg(a),
+        9 => a,
+        10 => u64::from(a > reg(b)),
+        11 => u64::from(reg(a) > b),
+        12 => u64::from(reg(a) > reg(b)),
+        13 => u64::from(a == reg(b)),
+        14 => u64::from(reg(a) == b),
+        15 => u64::from(reg(a) == reg(b)),
+        _ => panic!("unknown instruction {}", op),
+    };
+
+    regs[c as usize] = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs one instruction on registers `[5, 3, 5, 9]`.
+    fn run(op: usize, a: u64, b: u64, c: u64) -> [u64; 4] {
+        let mut regs = [5, 3, 5, 9];
+        execute(op, &mut regs, a, b, c);
+        regs
+    }
+
+    #[test]
+    fn addr() {
+        assert_eq!(run(0, 0, 1, 3), [5, 3, 5, 8]);
+    }
+
+    #[test]
+    fn addi() {
+        assert_eq!(run(1, 0, 7, 3), [5, 3, 5, 12]);
+    }
+
+    #[test]
+    fn mulr() {
+        assert_eq!(run(2, 0, 1, 3), [5, 3, 5, 15]);
+    }
+
+    #[test]
+    fn muli() {
+        assert_eq!(run(3, 0, 7, 3), [5, 3, 5, 35]);
+    }
+
+    #[test]
+    fn banr() {
+        assert_eq!(run(4, 0, 1, 3), [5, 3, 5, 1]);
+    }
+
+    #[test]
+    fn bani() {
+        assert_eq!(run(5, 0, 6, 3), [5, 3, 5, 4]);
+    }
+
+    #[test]
+    fn borr() {
+        assert_eq!(run(6, 0, 1, 3), [5, 3, 5, 7]);
+    }
+
+    #[test]
+    fn bori() {
+        assert_eq!(run(7, 0, 8, 3), [5, 3, 5, 13]);
+    }
+
+    #[test]
+    fn setr() {
+        assert_eq!(run(8, 1, 99, 3), [5, 3, 5, 3]);
+    }
+
+    #[test]
+    fn seti() {
+        assert_eq!(run(9, 42, 99, 3), [5, 3, 5, 42]);
+    }
+
+    #[test]
+    fn gtir() {
+        assert_eq!(run(10, 4, 1, 3), [5, 3, 5, 1]);
+    }
+
+    #[test]
+    fn gtri() {
+        assert_eq!(run(11, 1, 3, 3), [5, 3, 5, 0]);
+    }
+
+    #[test]
+    fn gtrr() {
+        assert_eq!(run(12, 0, 1, 3), [5, 3, 5, 1]);
+    }
+
+    #[test]
+    fn eqir() {
+        assert_eq!(run(13, 3, 1, 3), [5, 3, 5, 1]);
+    }
+
+    #[test]
+    fn eqri() {
+        assert_eq!(run(14, 0, 5, 3), [5, 3, 5, 1]);
+    }
+
+    #[test]
+    fn eqrr() {
+        assert_eq!(run(15, 0, 2, 3), [5, 3, 5, 1]);
+    }
+}